@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use rand::random;
+
+use std::{
+    env,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    settings::Preset,
+    world::{difficulty::Difficulty, World},
+};
+
+// The command line arguments of the game
+#[derive(Resource, Default)]
+pub struct Args {
+    pub seed: Option<u64>, // --seed <u64> or --daily: play the same world every run
+    pub replay: Option<PathBuf>, // --replay <file>: play a recorded run instead of live input
+    pub headless: Option<u32>, // --headless <runs>: let a bot fly that many seeds without a window
+    pub preset: Option<Preset>, // --preset <easy|normal|hard>: start with that preset instead of Normal
+}
+
+const USAGE: &str =
+    "Usage: delivery [--seed <u64> | --daily] [--replay <file> | --headless <runs>] \
+                     [--preset <easy|normal|hard>]";
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut iter = env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let value = iter.next().ok_or("Error: --seed needs a value")?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| format!("Error: invalid seed {value:?}"))?;
+                    args.seed = Some(seed);
+                }
+                // Everyone plays the same world today, so there is a ghost to race all day
+                "--daily" => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_err(|err| err.to_string())?;
+                    args.seed = Some(now.as_secs() / 86_400);
+                }
+                "--replay" => {
+                    let value = iter.next().ok_or("Error: --replay needs a file")?;
+                    args.replay = Some(PathBuf::from(value));
+                }
+                // The runs use consecutive seeds, starting at --seed or 0
+                "--headless" => {
+                    let value = iter
+                        .next()
+                        .ok_or("Error: --headless needs a number of runs")?;
+                    let runs = value
+                        .parse()
+                        .map_err(|_| format!("Error: invalid number of runs {value:?}"))?;
+                    args.headless = Some(runs);
+                }
+                "--preset" => {
+                    let value = iter.next().ok_or("Error: --preset needs a name")?;
+                    let preset = Preset::ALL
+                        .into_iter()
+                        .find(|preset| preset.name().eq_ignore_ascii_case(&value))
+                        .ok_or_else(|| format!("Error: unknown preset {value:?}\n{USAGE}"))?;
+                    args.preset = Some(preset);
+                }
+                _ => return Err(format!("Error: unknown argument {arg:?}\n{USAGE}")),
+            }
+        }
+        if args.replay.is_some() && args.headless.is_some() {
+            return Err(format!(
+                "Error: --replay and --headless can not be combined\n{USAGE}"
+            ));
+        }
+        Ok(args)
+    }
+
+    // A new world for a run, as hard as `difficulty` makes it. Without a fixed seed, every run gets a different world.
+    pub fn new_world(&self, difficulty: &Difficulty) -> World {
+        World::new(self.seed.unwrap_or_else(random), difficulty.clone())
+    }
+}
+
+// Whether every run flies the same world
+pub fn fixed_seed(args: Res<Args>) -> bool {
+    args.seed.is_some()
+}
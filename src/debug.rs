@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+
+use crate::{
+    stork::Stork,
+    tick::TickTransform,
+    world::collidables::{stork_shape, Collidable, StorkCollision},
+};
+
+// Draws the collision shapes over the game, toggled with F3
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShowShapes>()
+            .add_systems((toggle_shapes, draw_shapes).chain());
+    }
+}
+
+#[derive(Resource, Default)]
+struct ShowShapes(bool);
+
+// An edge or a contact mark, drawn by the debug view
+#[derive(Component)]
+struct ShapeEdge;
+
+const EDGE_WIDTH: f32 = 2.0; // in pixels
+const STORK_SHAPE_COLOR: Color = Color::rgb(0.2, 0.6, 1.0);
+const COLLIDABLE_SHAPE_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+const CONTACT_COLOR: Color = Color::rgb(1.0, 1.0, 0.2);
+const CONTACT_SIZE: f32 = 8.0; // in pixels
+
+fn toggle_shapes(keyboard_input: Res<Input<KeyCode>>, mut show_shapes: ResMut<ShowShapes>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        show_shapes.0 = !show_shapes.0;
+    }
+}
+
+// Redraws every shape each frame, as thin sprites along its edges, and marks where the stork touches something.
+// The stork is drawn where the simulation has it, which can be a fraction of a tick ahead of its sprite.
+fn draw_shapes(
+    mut commands: Commands,
+    show_shapes: Res<ShowShapes>,
+    mut collision_event_reader: EventReader<StorkCollision>,
+    edge_query: Query<Entity, With<ShapeEdge>>,
+    stork_query: Query<(&Stork, &TickTransform)>,
+    collidable_query: Query<(&Collidable, &Transform)>,
+) {
+    for entity in edge_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !show_shapes.0 {
+        collision_event_reader.clear();
+        return;
+    }
+    for collision in collision_event_reader.iter() {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: CONTACT_COLOR,
+                    custom_size: Some(Vec2::splat(CONTACT_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_translation(collision.contact_point.extend(10.0)),
+                ..default()
+            },
+            ShapeEdge,
+        ));
+    }
+    let stork_shapes = stork_query.iter().map(|(stork, tick_transform)| {
+        (
+            stork_shape(stork, &tick_transform.current),
+            STORK_SHAPE_COLOR,
+        )
+    });
+    let collidable_shapes = collidable_query
+        .iter()
+        .flat_map(|(collidable, transform)| collidable.world_shapes(transform))
+        .map(|shape| (shape, COLLIDABLE_SHAPE_COLOR));
+    for (shape, color) in stork_shapes.chain(collidable_shapes) {
+        for (i, &start) in shape.iter().enumerate() {
+            let edge = shape[(i + 1) % shape.len()] - start;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(edge.length(), EDGE_WIDTH)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation((start + edge / 2.0).extend(10.0))
+                        .with_rotation(Quat::from_rotation_z(edge.y.atan2(edge.x))),
+                    ..default()
+                },
+                ShapeEdge,
+            ));
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
+
+use std::mem;
+
+use crate::{
+    flight::FlightModel,
+    shapes,
+    stork::{Stork, StorkInput},
+    tick::TickTransform,
+    world::{
+        collidables::{Collidable, DeliveryTarget},
+        World,
+    },
+    PIXELS_PER_METER,
+};
+
+// A baby in a bundle, dropped by the stork. It falls until it hits a roof or the ground.
+#[derive(Component)]
+pub struct BabyBundle {
+    position: Vec2, // in meters
+    velocity: Vec2, // in meters per second
+}
+
+// The number of babies that landed on the right roof
+#[derive(Resource, Default)]
+pub struct Deliveries {
+    pub count: u32,
+}
+
+// Sent for every baby that landed on the right roof
+pub struct Delivered;
+
+const SIZE_BUNDLE: Vec2 = Vec2 { x: 20.0, y: 20.0 };
+const BUNDLE_COLOR: Color = Color::rgb(0.95, 0.95, 1.0);
+
+// Drop the bundle when space is pressed. The stork only gets a new bundle once the last one has landed.
+pub fn drop_bundle(
+    mut commands: Commands,
+    mut input: ResMut<StorkInput>,
+    mut stork_query: Query<&mut Stork>,
+) {
+    let mut stork = stork_query.get_single_mut().unwrap();
+    if !mem::take(&mut input.drop_bundle) || !stork.has_bundle {
+        return;
+    }
+    stork.has_bundle = false;
+    let transform = Transform::from_translation(
+        Vec3::new(stork.position.x, stork.position.y, 0.0) * PIXELS_PER_METER,
+    );
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: BUNDLE_COLOR,
+                custom_size: Some(SIZE_BUNDLE),
+                ..default()
+            },
+            transform,
+            ..default()
+        },
+        TickTransform::new(transform),
+        BabyBundle {
+            position: stork.position,
+            velocity: stork.velocity(),
+        },
+    ));
+}
+
+// The bundle keeps the velocity of the stork, and falls under the same gravity
+pub fn move_bundles(
+    fixed_time: Res<FixedTime>,
+    flight_model: Res<FlightModel>,
+    mut query: Query<(&mut BabyBundle, &mut TickTransform)>,
+) {
+    let dt = fixed_time.period.as_secs_f32();
+    for (mut bundle, mut tick_transform) in query.iter_mut() {
+        bundle.velocity.y -= flight_model.gravity * dt;
+        let velocity = bundle.velocity;
+        bundle.position += velocity * dt;
+        tick_transform.current.translation =
+            Vec3::new(bundle.position.x, bundle.position.y, 0.0) * PIXELS_PER_METER;
+    }
+}
+
+// A bundle that lands on the roof of a delivery target is delivered, and the world remembers that the house
+// got its baby. A bundle that hits any other part of a house, or the ground, is lost.
+pub fn bundle_landing(
+    mut commands: Commands,
+    bundle_query: Query<(Entity, &BabyBundle, &TickTransform)>,
+    mut collidable_query: Query<(
+        Entity,
+        &Collidable,
+        &Transform,
+        &mut Sprite,
+        Option<&DeliveryTarget>,
+    )>,
+    mut stork_query: Query<&mut Stork>,
+    mut deliveries: ResMut<Deliveries>,
+    mut world: ResMut<World>,
+    mut delivered_event_writer: EventWriter<Delivered>,
+) {
+    for (bundle_entity, bundle, bundle_transf) in bundle_query.iter() {
+        let bundle_transf = bundle_transf.current;
+        let mut landed = bundle.position.y <= 0.0;
+        for (house, collidable, collidable_transf, mut sprite, target) in
+            collidable_query.iter_mut()
+        {
+            let bundle_shape = shapes::rectangle(bundle_transf.translation.truncate(), SIZE_BUNDLE);
+            let Some((push, _)) = collidable.hit(collidable_transf, &bundle_shape) else {
+                continue;
+            };
+            landed = true;
+            if let (Collision::Top, Some(target)) = (shapes::side(push), target) {
+                world.deliver(target.tile_nr, target.house);
+                commands.entity(house).remove::<DeliveryTarget>();
+                sprite.color = Color::WHITE;
+                deliveries.count += 1;
+                delivered_event_writer.send(Delivered);
+            }
+            break;
+        }
+        if landed {
+            commands.entity(bundle_entity).despawn();
+            if let Ok(mut stork) = stork_query.get_single_mut() {
+                stork.has_bundle = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{catalogue::CollidableCatalogue, collidables::spawn_collidable};
+    use bevy::ecs::system::CommandQueue;
+
+    // A house_2 standing on the ground at the origin, with its flat roof 72 pixels above its center
+    fn app_with_house(target: bool) -> (App, Entity, Vec2) {
+        let mut app = App::new();
+        app.init_resource::<Deliveries>()
+            .insert_resource(World::new(0, default()))
+            .add_event::<Delivered>()
+            .add_system(bundle_landing);
+        let catalogue = CollidableCatalogue::read().unwrap();
+        let def = catalogue.get("house_2").unwrap();
+        let center = Vec2::new(0.0, def.ground_height());
+        let mut queue = CommandQueue::default();
+        let house = spawn_collidable(
+            &mut Commands::new(&mut queue, &app.world),
+            "house_2",
+            def,
+            center,
+            None,
+        );
+        queue.apply(&mut app.world);
+        if target {
+            app.world.entity_mut(house).insert(DeliveryTarget {
+                tile_nr: 0,
+                house: 0,
+            });
+        }
+        (app, house, center)
+    }
+
+    // Lets a bundle at `position`, in pixels, land during one tick. Whether it landed at all.
+    fn drop_at(app: &mut App, position: Vec2) -> bool {
+        let bundle = app
+            .world
+            .spawn((
+                BabyBundle {
+                    position: position / PIXELS_PER_METER,
+                    velocity: Vec2::ZERO,
+                },
+                TickTransform::new(Transform::from_translation(position.extend(0.0))),
+            ))
+            .id();
+        app.update();
+        app.world.get_entity(bundle).is_none()
+    }
+
+    #[test]
+    fn only_a_bundle_on_the_roof_of_a_target_is_delivered() {
+        // Just below the roof, overlapping it by a few pixels
+        let on_roof = |center: Vec2| center + Vec2::new(0.0, 72.0 + SIZE_BUNDLE.y / 2.0 - 3.0);
+
+        let (mut app, house, center) = app_with_house(true);
+        assert!(drop_at(&mut app, on_roof(center)));
+        assert_eq!(app.world.resource::<Deliveries>().count, 1);
+        assert!(app.world.get::<DeliveryTarget>(house).is_none());
+        assert!(app.world.resource::<World>().is_delivered(0, 0));
+        // The house has its baby, so the next bundle on the roof is lost
+        assert!(drop_at(&mut app, on_roof(center)));
+        assert_eq!(app.world.resource::<Deliveries>().count, 1);
+
+        // Against the wall
+        let (mut app, _, center) = app_with_house(true);
+        assert!(drop_at(
+            &mut app,
+            center + Vec2::new(-60.0 - SIZE_BUNDLE.x / 2.0 + 3.0, 0.0)
+        ));
+        assert_eq!(app.world.resource::<Deliveries>().count, 0);
+
+        // On a house that isn't waiting for a baby
+        let (mut app, _, center) = app_with_house(false);
+        assert!(drop_at(&mut app, on_roof(center)));
+        assert_eq!(app.world.resource::<Deliveries>().count, 0);
+
+        // On the ground beside the target
+        let (mut app, _, _) = app_with_house(true);
+        assert!(drop_at(&mut app, Vec2::new(200.0, -1.0)));
+        assert_eq!(app.world.resource::<Deliveries>().count, 0);
+
+        // Still falling above the roof
+        let (mut app, _, center) = app_with_house(true);
+        assert!(!drop_at(&mut app, center + Vec2::new(0.0, 200.0)));
+        assert_eq!(app.world.resource::<Deliveries>().count, 0);
+    }
+}
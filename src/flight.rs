@@ -0,0 +1,581 @@
+use bevy::prelude::*;
+
+// The part of the stork that the flight model moves
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlightState {
+    pub position: Vec2, // in meters
+    pub speed: f32,     // in meters per second
+    pub direction: f32, // in degrees, 90 is to the right, 0 is down, -90 is to the left
+}
+
+// The unit vector along which a stork with `direction` flies, in degrees as in FlightState
+pub fn heading(direction: f32) -> Vec2 {
+    // 0 is down rather than to the right, so turn back a quarter to get the angle on the unit circle
+    let angle = (direction - 90.0).to_radians();
+    Vec2::new(angle.cos(), angle.sin())
+}
+
+// What the player (or anything else flying the stork) asks for during one step
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlightControls {
+    pub thrust: bool,
+    pub pitch_up: bool,
+    pub pitch_down: bool,
+}
+
+// How much the stork can still flap. Once it runs out, the stork is exhausted and can't flap at all
+// until it has recovered enough to be rested again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stamina {
+    pub level: f32, // 1 is fresh, 0 is empty
+    pub exhausted: bool,
+}
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self {
+            level: 1.0,
+            exhausted: false,
+        }
+    }
+}
+
+// What the ground did to the stork during one step
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroundContact {
+    Airborne,
+    Landed, // touched down gently, or still resting or rolling on the ground
+    Crashed,
+}
+
+const AIR_RESISTANCE: f32 = 0.1;
+const GRAVITY: f32 = 9.81 * 0.2;
+const STALL_SPEED: f32 = 1.0;
+
+const MAX_ACCELLEATION: f32 = 10.0;
+const TOP_ACCELERATION_SPEED: f32 = 4.0;
+const TURN_SPEED: f32 = 100.0;
+const MAX_STALL_TURN_SPEED: f32 = 360.0;
+
+const MAX_LANDING_SPEED: f32 = 3.0;
+const MAX_LANDING_ANGLE: f32 = 20.0;
+const GROUND_FRICTION: f32 = 2.0;
+const TAKE_OFF_SPEED: f32 = 2.5;
+const TAKE_OFF_ANGLE: f32 = 45.0;
+
+const STAMINA_DRAIN: f32 = 0.25;
+const GLIDING_RECOVERY: f32 = 0.05;
+const RESTING_RECOVERY: f32 = 0.4;
+const RESTED_STAMINA: f32 = 0.25;
+
+// The physics of the stork, without anything Bevy specific, so it can be reasoned about and tested on its own
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct FlightModel {
+    pub air_resistance: f32,
+    pub gravity: f32,
+    pub stall_speed: f32,
+    pub max_acceleration: f32,
+    pub top_acceleration_speed: f32,
+    pub turn_speed: f32,
+    pub max_landing_speed: f32, // in meters per second
+    pub max_landing_angle: f32, // in degrees below horizontal
+    pub ground_friction: f32,   // like air resistance, but while on the ground
+    pub take_off_speed: f32,    // in meters per second
+    pub take_off_angle: f32,    // in degrees above horizontal
+    pub stamina_drain: f32,     // of a full stamina bar per second of thrust
+    pub gliding_recovery: f32,  // of a full stamina bar per second in the air without thrust
+    pub resting_recovery: f32,  // of a full stamina bar per second standing on the ground or a roof
+    pub rested_stamina: f32,    // that an exhausted stork needs before it can flap again
+    pub auto_level_speed: f32, // in degrees per second towards level flight without pitch input, 0 for none
+}
+
+impl Default for FlightModel {
+    fn default() -> Self {
+        Self {
+            air_resistance: AIR_RESISTANCE,
+            gravity: GRAVITY,
+            stall_speed: STALL_SPEED,
+            max_acceleration: MAX_ACCELLEATION,
+            top_acceleration_speed: TOP_ACCELERATION_SPEED,
+            turn_speed: TURN_SPEED,
+            max_landing_speed: MAX_LANDING_SPEED,
+            max_landing_angle: MAX_LANDING_ANGLE,
+            ground_friction: GROUND_FRICTION,
+            take_off_speed: TAKE_OFF_SPEED,
+            take_off_angle: TAKE_OFF_ANGLE,
+            stamina_drain: STAMINA_DRAIN,
+            gliding_recovery: GLIDING_RECOVERY,
+            resting_recovery: RESTING_RECOVERY,
+            rested_stamina: RESTED_STAMINA,
+            auto_level_speed: 0.0,
+        }
+    }
+}
+
+impl FlightModel {
+    pub fn step(&self, state: FlightState, controls: FlightControls, dt: f32) -> FlightState {
+        let mut speed = state.speed;
+        let mut direction = state.direction;
+
+        // apply power on keyboard input (shift)
+        // At speed 0, the acceleration is maximum. At speed TOP_ACCELERATION_SPEED, the acceleration is 0
+        // At speeds beyond TOP_ACCELERATION_SPEED, the acceleration is negative
+        // The speed follows the exact solution of that, so a long step can't overshoot the top speed or, from
+        // above it, brake below zero. At the tick rate it is the same as adding acceleration * dt.
+        if controls.thrust {
+            let rate = self.max_acceleration / self.top_acceleration_speed;
+            speed = self.top_acceleration_speed
+                + (speed - self.top_acceleration_speed) * (-rate * dt).exp();
+        }
+
+        // on using the up and down arrows, change the direction
+        if controls.pitch_up {
+            direction += self.turn_speed * direction.signum() * dt;
+            if direction.abs() > 175.0 {
+                direction = -direction.signum() * 180.0;
+            }
+        }
+        if controls.pitch_down {
+            direction -= self.turn_speed * direction.signum() * dt;
+            if direction.abs() < 5.0 {
+                direction = -direction.signum() * 0.0;
+            }
+        }
+
+        // without the up and down arrows, the auto-level assist turns back towards level flight
+        if self.auto_level_speed > 0.0 && !controls.pitch_up && !controls.pitch_down {
+            let climb_angle = direction.abs() - 90.0;
+            let turn = (self.auto_level_speed * dt).min(climb_angle.abs());
+            direction -= turn * climb_angle.signum() * direction.signum();
+        }
+
+        // apply gravity. Change the speed based on conservations of energy
+        // When a climb costs more energy than the stork has, it stops and stalls instead
+        let climb = (direction.abs() - 90.0).to_radians().sin();
+        let delta_h = climb * speed * dt;
+        speed = (speed.powi(2) - 2.0 * (delta_h * self.gravity))
+            .max(0.0)
+            .sqrt();
+        // Near a standstill the energy balance has nothing to work with, so gravity pulls directly
+        speed = speed.max(-climb * self.gravity * dt);
+        // apply air resistance. It decays the speed exactly, so no step is long enough to make it negative.
+        // At the tick rate it is the same as subtracting speed * air_resistance * dt.
+        speed *= (-self.air_resistance * dt).exp();
+        // turn downward if speed is below stall speed
+        if speed < self.stall_speed {
+            let dir = direction.signum();
+            let mut elevation = direction.abs();
+            // use a curve to make the turn more gradual
+            // At the stall speed, the turn speed is 0, at speed 0, the turn speed is at its maximum
+            let turnspeed = (20.0 / (speed / self.stall_speed).powi(2)).min(MAX_STALL_TURN_SPEED);
+            // Falling straight down is as far as a stall can turn
+            elevation = (elevation - turnspeed * dt).max(0.0);
+            direction = elevation * dir;
+        }
+        let position = state.position + heading(direction) * speed * dt;
+        FlightState {
+            position,
+            speed,
+            direction,
+        }
+    }
+
+    // Flapping costs stamina. Without any left there is no thrust, so the stork has to glide or rest
+    // until it recovers.
+    pub fn use_stamina(
+        &self,
+        mut stamina: Stamina,
+        mut controls: FlightControls,
+        resting: bool,
+        dt: f32,
+    ) -> (Stamina, FlightControls) {
+        controls.thrust &= !stamina.exhausted;
+        let change = if controls.thrust {
+            -self.stamina_drain
+        } else if resting {
+            self.resting_recovery
+        } else {
+            self.gliding_recovery
+        };
+        stamina.level = (stamina.level + change * dt).clamp(0.0, 1.0);
+        if stamina.level == 0.0 {
+            stamina.exhausted = true;
+        } else if stamina.level >= self.rested_stamina {
+            stamina.exhausted = false;
+        }
+        (stamina, controls)
+    }
+
+    // The stork keeps its momentum when the air around it changes by `change`, so its airspeed changes instead.
+    // A gust from behind slows it down in the air, a gust from the front speeds it up.
+    pub fn feel_wind_change(&self, mut state: FlightState, change: Vec2) -> FlightState {
+        state.speed = (state.speed - change.dot(heading(state.direction))).max(0.0);
+        state
+    }
+
+    // Whether touching down in this state is a landing rather than a crash: slow, and not too steep
+    pub fn can_land(&self, state: FlightState) -> bool {
+        let descent_angle = 90.0 - state.direction.abs();
+        descent_angle <= self.max_landing_angle && state.speed <= self.max_landing_speed
+    }
+
+    // Jumps up from standing, to get flying without a run-up
+    pub fn take_off(&self, mut state: FlightState) -> FlightState {
+        if state.speed < self.take_off_speed {
+            state.speed = self.take_off_speed;
+            state.direction = state.direction.signum() * (90.0 + self.take_off_angle);
+        }
+        state
+    }
+
+    // Keeps a stepped state on or above the ground, which is at `ground_height` for the center of the stork.
+    // Touching down slow and shallow is a landing, anything else is a crash. Once on the ground, the stork
+    // rests or rolls level until it climbs away.
+    pub fn touch_ground(
+        &self,
+        mut state: FlightState,
+        on_ground: bool,
+        ground_height: f32,
+        dt: f32,
+    ) -> (FlightState, GroundContact) {
+        if state.position.y > ground_height {
+            return (state, GroundContact::Airborne);
+        }
+        if !on_ground && !self.can_land(state) {
+            return (state, GroundContact::Crashed);
+        }
+        state.position.y = ground_height;
+        if state.direction.abs() < 90.0 {
+            state.direction = 90.0 * state.direction.signum();
+        }
+        state.speed *= (-self.ground_friction * dt).exp();
+        (state, GroundContact::Landed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const DT: f32 = 1.0 / 120.0;
+
+    fn fly(
+        model: &FlightModel,
+        mut state: FlightState,
+        controls: FlightControls,
+        steps: usize,
+    ) -> FlightState {
+        for _ in 0..steps {
+            state = model.step(state, controls, DT);
+        }
+        state
+    }
+
+    #[test]
+    fn thrust_and_air_resistance_match_the_linear_steps_at_the_tick_rate() {
+        let model = FlightModel::default();
+        let thrust = FlightControls {
+            thrust: true,
+            ..default()
+        };
+        // One step the linear way: speed += acceleration * dt, then speed -= speed * air_resistance * dt
+        let linear = |speed: f32, dt: f32| {
+            let acceleration =
+                model.max_acceleration * (1.0 - speed / model.top_acceleration_speed);
+            let speed = speed + acceleration * dt;
+            speed - speed * model.air_resistance * dt
+        };
+        for speed in [0.0, 1.0, 2.5, 4.0, 6.0, 10.0] {
+            let state = FlightState {
+                position: Vec2::new(0.0, 10.0),
+                speed,
+                direction: 90.0,
+            };
+            let next = model.step(state, thrust, DT);
+            assert!((next.speed - linear(speed, DT)).abs() < 2e-3, "{speed}");
+        }
+        // A long step from far above the top speed brakes the linear way to below zero
+        let fast = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 50.0,
+            direction: 90.0,
+        };
+        assert!(linear(fast.speed, 0.5) < 0.0);
+        assert!(model.step(fast, thrust, 0.5).speed >= model.top_acceleration_speed);
+    }
+
+    #[test]
+    fn stall_drops_the_nose() {
+        let model = FlightModel::default();
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 0.5 * STALL_SPEED,
+            direction: 90.0,
+        };
+        let next = model.step(state, FlightControls::default(), DT);
+        assert!(next.direction < state.direction, "{next:?}");
+        assert!(
+            next.direction > 0.0,
+            "a stall keeps the stork facing the same way"
+        );
+    }
+
+    #[test]
+    fn no_stall_above_stall_speed() {
+        let model = FlightModel {
+            air_resistance: 0.0,
+            ..default()
+        };
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 2.0 * STALL_SPEED,
+            direction: 90.0,
+        };
+        let next = model.step(state, FlightControls::default(), DT);
+        assert_eq!(next.direction, state.direction);
+        assert_eq!(next.speed, state.speed);
+    }
+
+    #[test]
+    fn dive_conserves_energy() {
+        let model = FlightModel {
+            air_resistance: 0.0,
+            ..default()
+        };
+        let start = FlightState {
+            position: Vec2::new(0.0, 100.0),
+            speed: 2.0,
+            direction: 30.0,
+        };
+        let end = fly(&model, start, FlightControls::default(), 240);
+        let energy =
+            |state: &FlightState| 0.5 * state.speed.powi(2) + model.gravity * state.position.y;
+        assert!(end.position.y < start.position.y);
+        assert!(end.speed > start.speed);
+        assert!((energy(&end) - energy(&start)).abs() < 0.01 * energy(&start));
+    }
+
+    #[test]
+    fn pitching_up_past_vertical_wraps_to_the_other_side() {
+        let model = FlightModel::default();
+        let controls = FlightControls {
+            pitch_up: true,
+            ..default()
+        };
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 4.0,
+            direction: 174.0,
+        };
+        let next = model.step(state, controls, 0.05);
+        assert_eq!(next.direction, -180.0);
+
+        let state = FlightState {
+            direction: -174.0,
+            ..state
+        };
+        let next = model.step(state, controls, 0.05);
+        assert_eq!(next.direction, 180.0);
+    }
+
+    #[test]
+    fn pitching_down_past_vertical_wraps_to_the_other_side() {
+        let model = FlightModel::default();
+        let controls = FlightControls {
+            pitch_down: true,
+            ..default()
+        };
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 4.0,
+            direction: 6.0,
+        };
+        let next = model.step(state, controls, 0.05);
+        assert_eq!(next.direction, 0.0);
+        assert!(next.direction.is_sign_negative());
+    }
+
+    #[test]
+    fn steep_slow_climb_stalls_instead_of_failing() {
+        let model = FlightModel::default();
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 0.1,
+            direction: 175.0,
+        };
+        let next = model.step(state, FlightControls::default(), 0.1);
+        assert_eq!(next.speed, 0.0);
+        assert!(next.direction < state.direction);
+        // Once out of energy, the stork falls and picks up speed again
+        let end = fly(&model, next, FlightControls::default(), 240);
+        assert!(end.speed > 0.0);
+        assert!(end.position.y < state.position.y);
+    }
+
+    #[test]
+    fn gentle_touchdown_lands_and_steep_one_crashes() {
+        let model = FlightModel::default();
+        let state = FlightState {
+            position: Vec2::new(0.0, -0.01),
+            speed: 0.5 * MAX_LANDING_SPEED,
+            direction: 90.0 - 0.5 * MAX_LANDING_ANGLE,
+        };
+        let (landed, contact) = model.touch_ground(state, false, 0.0, DT);
+        assert_eq!(contact, GroundContact::Landed);
+        assert_eq!(landed.position.y, 0.0);
+        assert_eq!(landed.direction, 90.0);
+
+        let steep = FlightState {
+            direction: 90.0 - 2.0 * MAX_LANDING_ANGLE,
+            ..state
+        };
+        let (_, contact) = model.touch_ground(steep, false, 0.0, DT);
+        assert_eq!(contact, GroundContact::Crashed);
+
+        let fast = FlightState {
+            speed: 2.0 * MAX_LANDING_SPEED,
+            ..state
+        };
+        let (_, contact) = model.touch_ground(fast, false, 0.0, DT);
+        assert_eq!(contact, GroundContact::Crashed);
+    }
+
+    #[test]
+    fn thrust_on_the_ground_takes_off() {
+        let model = FlightModel::default();
+        let mut state = FlightState {
+            position: Vec2::new(0.0, 0.0),
+            speed: 0.0,
+            direction: 90.0,
+        };
+        let mut contact = GroundContact::Landed;
+        for tick in 0..(10.0 / DT) as usize {
+            let controls = FlightControls {
+                thrust: true,
+                // Pull up once there is enough speed to climb
+                pitch_up: tick as f32 * DT > 1.0,
+                ..default()
+            };
+            let stepped = model.step(state, controls, DT);
+            (state, contact) =
+                model.touch_ground(stepped, contact == GroundContact::Landed, 0.0, DT);
+            assert_ne!(contact, GroundContact::Crashed, "{state:?}");
+        }
+        assert_eq!(contact, GroundContact::Airborne);
+        assert!(state.position.y > 1.0, "{state:?}");
+    }
+
+    #[test]
+    fn thrust_runs_out_of_stamina_and_resting_recovers_it() {
+        let model = FlightModel::default();
+        let thrust = FlightControls {
+            thrust: true,
+            ..default()
+        };
+        let mut stamina = Stamina::default();
+        for _ in 0..(1.0 / STAMINA_DRAIN / DT) as usize + 1 {
+            (stamina, _) = model.use_stamina(stamina, thrust, false, DT);
+        }
+        assert!(stamina.exhausted, "{stamina:?}");
+        // An exhausted stork can't flap, not even a little, until it has rested
+        for _ in 0..(RESTED_STAMINA / RESTING_RECOVERY / DT) as usize - 1 {
+            let controls;
+            (stamina, controls) = model.use_stamina(stamina, thrust, true, DT);
+            assert!(!controls.thrust, "{stamina:?}");
+        }
+        for _ in 0..4 {
+            (stamina, _) = model.use_stamina(stamina, FlightControls::default(), true, DT);
+        }
+        assert!(!stamina.exhausted, "{stamina:?}");
+
+        let empty = Stamina {
+            level: 0.0,
+            exhausted: true,
+        };
+        let (glided, _) = model.use_stamina(empty, FlightControls::default(), false, 1.0);
+        let (rested, _) = model.use_stamina(empty, FlightControls::default(), true, 1.0);
+        assert!(
+            rested.level > glided.level,
+            "resting recovers faster than gliding"
+        );
+    }
+
+    #[test]
+    fn auto_level_levels_out_without_pitch_input() {
+        let model = FlightModel {
+            auto_level_speed: 40.0,
+            ..default()
+        };
+        for direction in [60.0, 120.0, -60.0, -120.0] {
+            let state = FlightState {
+                position: Vec2::new(0.0, 100.0),
+                speed: 5.0 * STALL_SPEED,
+                direction,
+            };
+            let leveled = fly(&model, state, FlightControls::default(), 120);
+            assert_eq!(leveled.direction, 90.0 * direction.signum(), "{direction}");
+            let pitching = FlightControls {
+                pitch_up: true,
+                ..default()
+            };
+            let next = model.step(state, pitching, DT);
+            let manual = FlightModel::default().step(state, pitching, DT);
+            assert_eq!(next.direction, manual.direction, "{direction}");
+        }
+    }
+
+    #[test]
+    fn gusts_change_the_airspeed() {
+        let model = FlightModel::default();
+        let state = FlightState {
+            position: Vec2::new(0.0, 10.0),
+            speed: 3.0,
+            direction: 90.0,
+        };
+        let tailwind = model.feel_wind_change(state, Vec2::new(1.0, 0.0));
+        let headwind = model.feel_wind_change(state, Vec2::new(-1.0, 0.0));
+        assert_eq!(tailwind.speed, 2.0);
+        assert_eq!(headwind.speed, 4.0);
+        // A stork flying left feels it the other way around
+        let left = FlightState {
+            direction: -90.0,
+            ..state
+        };
+        assert_eq!(model.feel_wind_change(left, Vec2::new(1.0, 0.0)).speed, 4.0);
+        assert_eq!(
+            model.feel_wind_change(state, Vec2::new(-10.0, 0.0)).speed,
+            10.0 + state.speed
+        );
+        assert_eq!(
+            model.feel_wind_change(state, Vec2::new(10.0, 0.0)).speed,
+            0.0
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn speed_stays_finite_and_non_negative(
+            speed in 0.0f32..50.0,
+            direction in -180.0f32..=180.0,
+            dt in 0.0f32..0.5,
+            thrust: bool,
+            pitch_up: bool,
+            pitch_down: bool,
+        ) {
+            let model = FlightModel::default();
+            let controls = FlightControls { thrust, pitch_up, pitch_down };
+            let mut state = FlightState {
+                position: Vec2::new(0.0, 10.0),
+                speed,
+                direction,
+            };
+            for _ in 0..10 {
+                state = model.step(state, controls, dt);
+                prop_assert!(state.speed.is_finite() && state.speed >= 0.0, "{state:?}");
+                prop_assert!(state.direction.abs() <= 180.0, "{state:?}");
+                prop_assert!(state.position.is_finite(), "{state:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use bevy::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    args::Args,
+    delivery::{BabyBundle, Deliveries},
+    ghost::Ghost,
+    score::Score,
+    stork::Stork,
+    world::{
+        collidables::{Collidable, StorkCollision},
+        difficulty::Difficulty,
+        tiles::Tile,
+        wind::Wind,
+        World,
+    },
+};
+
+#[derive(States, Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+// What ended a run
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crash {
+    Collidable(String), // its name in the collidable catalogue
+    Ground,             // touched down too fast or too steep
+}
+
+// How the current run ended, if it did. A run that was stopped or ran out of replay has no crash.
+#[derive(Resource, Default)]
+pub struct RunOutcome {
+    pub crash: Option<Crash>,
+    ended: bool,
+}
+
+impl RunOutcome {
+    // Ends the run in this tick. The state only changes after the frame, so the ticks that are left in the frame
+    // don't run at all, and the run comes out the same however many ticks a frame has. The first end counts.
+    pub fn end(&mut self, crash: Option<Crash>, next_state: &mut NextState<GameState>) {
+        if self.ended {
+            return;
+        }
+        self.ended = true;
+        self.crash = crash;
+        next_state.set(GameState::GameOver);
+    }
+}
+
+// Run condition for the simulation: true until the run has ended
+pub fn run_going(run_outcome: Res<RunOutcome>) -> bool {
+    !run_outcome.ended
+}
+
+// Everything that is spawned during a run
+type RunEntity = Or<(
+    With<Stork>,
+    With<Ghost>,
+    With<Tile>,
+    With<Collidable>,
+    With<BabyBundle>,
+)>;
+
+// Clear everything that belongs to a run, so a new run starts from a fresh world
+pub fn end_run(
+    mut commands: Commands,
+    args: Res<Args>,
+    difficulty: Res<Difficulty>,
+    mut world: ResMut<World>,
+    mut deliveries: ResMut<Deliveries>,
+    run_query: Query<Entity, RunEntity>,
+    mut camera_query: Query<&mut Transform, With<Camera2d>>,
+) {
+    for entity in run_query.iter() {
+        commands.entity(entity).despawn();
+    }
+    *world = args.new_world(&difficulty);
+    *deliveries = Deliveries::default();
+    commands.insert_resource(Score::default());
+    commands.insert_resource(RunOutcome::default());
+    commands.insert_resource(Wind::default());
+    // There is no camera in a headless run
+    if let Ok(mut camera) = camera_query.get_single_mut() {
+        camera.translation.x = 0.0;
+    }
+}
+
+// Run condition that is true when no run is in progress, i.e. when entering Playing should start a new one
+pub fn no_stork(stork_query: Query<(), With<Stork>>) -> bool {
+    stork_query.is_empty()
+}
+
+// Any collision with something lethal ends the run, unless the stork survives it
+pub fn game_over_on_collision(
+    mut collision_event_reader: EventReader<StorkCollision>,
+    collidable_query: Query<&Collidable>,
+    mut stork_query: Query<&mut Stork>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut run_outcome: ResMut<RunOutcome>,
+) {
+    // Standing on a roof is fine, flying into any other part of it is not.
+    // All hits are read, so none are left over for the next tick.
+    let mut stork = stork_query.get_single_mut().unwrap();
+    let lethal_collisions: Vec<&StorkCollision> = collision_event_reader
+        .iter()
+        .filter(|collision| {
+            !stork.perched_on(collision.collidable)
+                && collidable_query
+                    .get(collision.collidable)
+                    .is_ok_and(|collidable| collidable.lethal)
+        })
+        .collect();
+    let Some(collision) = lethal_collisions.first() else {
+        return;
+    };
+    if !stork.survive_hit() {
+        run_outcome.end(
+            Some(Crash::Collidable(collision.kind.clone())),
+            &mut next_state,
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+
+use std::{fs, mem, path::PathBuf};
+
+use crate::{
+    data_dir,
+    replay::{take, take_array},
+    score::Score,
+    settings::{GameSettings, Preset},
+    stork::{stork_transform, Stork},
+    tick::TickTransform,
+    world::World,
+};
+
+// The path of the stork during a run, one point per tick
+pub struct Trajectory {
+    pub distance: f32,        // the furthest the stork got, in meters
+    points: Vec<(Vec2, f32)>, // position in meters and direction in degrees
+}
+
+// The personal best on this seed and preset that is being raced, and the run that might replace it
+#[derive(Resource, Default)]
+pub struct GhostRace {
+    pub best: Option<Trajectory>,
+    current: Vec<(Vec2, f32)>,
+}
+
+// The see-through stork that flies the personal best
+#[derive(Component)]
+pub struct Ghost;
+
+const GHOST_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+
+const MAGIC: &[u8; 4] = b"DLVG";
+// Bump this when the layout of the file changes
+const FORMAT_VERSION: u8 = 1;
+
+// Every seed has its own ghost per preset, so a run on Easy is never raced on Hard
+fn ghost_path(seed: u64, preset: Preset) -> Option<PathBuf> {
+    let preset = preset.name().to_lowercase();
+    data_dir().map(|dir| dir.join("ghosts").join(format!("{seed}-{preset}.ghost")))
+}
+
+impl Trajectory {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bytes.extend(self.distance.to_le_bytes());
+        bytes.extend((self.points.len() as u32).to_le_bytes());
+        for (position, direction) in &self.points {
+            bytes.extend(position.x.to_le_bytes());
+            bytes.extend(position.y.to_le_bytes());
+            bytes.extend(direction.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn decode(mut bytes: &[u8]) -> Result<Self, String> {
+        let bytes = &mut bytes;
+        let read_f32 = |bytes: &mut &[u8]| take_array(bytes).map(f32::from_le_bytes);
+        if take(bytes, MAGIC.len())? != MAGIC {
+            return Err("not a ghost file".to_string());
+        }
+        let format_version = take(bytes, 1)?[0];
+        if format_version != FORMAT_VERSION {
+            return Err(format!("unsupported ghost format {format_version}"));
+        }
+        let distance = read_f32(bytes)?;
+        let count = u32::from_le_bytes(take_array(bytes)?);
+        let mut points = Vec::new();
+        for _ in 0..count {
+            let position = Vec2::new(read_f32(bytes)?, read_f32(bytes)?);
+            points.push((position, read_f32(bytes)?));
+        }
+        Ok(Self { distance, points })
+    }
+
+    fn load(seed: u64, preset: Preset) -> Option<Self> {
+        let path = ghost_path(seed, preset)?;
+        let bytes = fs::read(&path).ok()?;
+        Self::decode(&bytes)
+            .map_err(|err| warn!("Could not load the ghost from {}: {err}", path.display()))
+            .ok()
+    }
+
+    fn save(&self, seed: u64, preset: Preset) -> Result<(), String> {
+        let Some(path) = ghost_path(seed, preset) else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, self.encode()).map_err(|err| err.to_string())
+    }
+}
+
+impl GhostRace {
+    // Where the ghost was at the same tick as the stork. It stays where its run ended.
+    fn ghost_point(&self) -> Option<(Vec2, f32)> {
+        let best = self.best.as_ref()?;
+        best.points
+            .get(self.current.len().saturating_sub(1))
+            .or_else(|| best.points.last())
+            .copied()
+    }
+
+    // How many meters the stork is ahead of the ghost, negative when it is behind
+    pub fn lead(&self, stork: &Stork) -> Option<f32> {
+        let (ghost_position, _) = self.ghost_point()?;
+        Some(stork.position.x - ghost_position.x)
+    }
+}
+
+// Runs when a run starts. Loads the personal best of this seed and preset, and lets a ghost fly it.
+pub fn start_ghost_race(
+    mut commands: Commands,
+    mut ghost_race: ResMut<GhostRace>,
+    world: Res<World>,
+    settings: Res<GameSettings>,
+    asset_server: Res<AssetServer>,
+) {
+    *ghost_race = GhostRace {
+        best: Trajectory::load(world.seed(), settings.preset),
+        ..default()
+    };
+    let Some((position, direction)) = ghost_race.ghost_point() else {
+        return;
+    };
+    let mut transform = stork_transform(position, direction);
+    transform.translation.z = -0.1; // behind the stork, in front of the houses
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: GHOST_COLOR,
+                ..default()
+            },
+            transform,
+            texture: asset_server.load("sprites\\stork.png"),
+            ..default()
+        },
+        TickTransform::new(transform),
+        Ghost,
+    ));
+}
+
+// Runs every tick after the stork moved. Records the stork and moves the ghost along.
+pub fn ghost_tick(
+    mut ghost_race: ResMut<GhostRace>,
+    stork_query: Query<&Stork>,
+    mut ghost_query: Query<&mut TickTransform, With<Ghost>>,
+) {
+    let stork = stork_query.get_single().unwrap();
+    ghost_race.current.push((stork.position, stork.direction()));
+    if let (Some((position, direction)), Ok(mut tick_transform)) =
+        (ghost_race.ghost_point(), ghost_query.get_single_mut())
+    {
+        let z = tick_transform.current.translation.z;
+        tick_transform.current = stork_transform(position, direction);
+        tick_transform.current.translation.z = z;
+    }
+}
+
+// A run that got further than the ghost becomes the new ghost of this seed and preset. Only runs on a fixed
+// seed are saved: a random one is never flown again, so its ghost would only fill up the disk.
+pub fn save_ghost(
+    mut ghost_race: ResMut<GhostRace>,
+    score: Res<Score>,
+    world: Res<World>,
+    settings: Res<GameSettings>,
+) {
+    let ghost_still_best = ghost_race
+        .best
+        .as_ref()
+        .is_some_and(|best| best.distance >= score.distance);
+    if ghost_still_best {
+        return;
+    }
+    let trajectory = Trajectory {
+        distance: score.distance,
+        points: mem::take(&mut ghost_race.current),
+    };
+    match trajectory.save(world.seed(), settings.preset) {
+        Ok(()) => ghost_race.best = Some(trajectory),
+        Err(err) => warn!("Could not save the ghost: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trajectory() -> Trajectory {
+        Trajectory {
+            distance: 12.5,
+            points: vec![
+                (Vec2::new(0.0, 3.0), 100.0),
+                (Vec2::new(0.02, 3.01), 99.5),
+                (Vec2::new(-4.0, 0.16), -90.0),
+            ],
+        }
+    }
+
+    #[test]
+    fn decoding_an_encoded_ghost_gives_it_back() {
+        let ghost = trajectory();
+        let decoded = Trajectory::decode(&ghost.encode()).unwrap();
+        assert_eq!(decoded.distance, ghost.distance);
+        assert_eq!(decoded.points, ghost.points);
+    }
+
+    #[test]
+    fn broken_ghosts_are_rejected() {
+        let bytes = trajectory().encode();
+        for len in 0..bytes.len() {
+            assert!(Trajectory::decode(&bytes[..len]).is_err(), "{len}");
+        }
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(Trajectory::decode(&bad_magic).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use serde::Serialize;
+
+use std::{mem, process, time::Instant};
+
+use crate::{
+    args::Args,
+    delivery::Deliveries,
+    flight::{FlightControls, FlightModel},
+    game_state::{run_going, Crash, GameState, RunOutcome},
+    replay::{replay_tick, InputSource, Replay},
+    score::Score,
+    settings::GameSettings,
+    simulation::SimulationPlugin,
+    stork::{Stork, StorkInput},
+    tick::TICK_RATE,
+    world::{
+        catalogue::CollidableCatalogue,
+        collidables::{Collidable, DeliveryTarget},
+        difficulty::Difficulty,
+        layouts::TileLayouts,
+        World,
+    },
+    PIXELS_PER_METER,
+};
+
+// The bot flies just above the highest roof, at a speed it can hold level
+const CRUISE_HEIGHT: f32 = 4.5; // in meters
+const CRUISE_SPEED: f32 = 3.0; // in meters per second
+const MAX_CLIMB_ANGLE: f32 = 30.0; // in degrees
+
+// A bot that never crashes still has to stop some time
+const MAX_TICKS: u32 = 5 * 60 * TICK_RATE as u32;
+
+// The result of one headless run
+#[derive(Serialize)]
+struct RunStats {
+    seed: u64,
+    distance: f32, // in meters
+    deliveries: u32,
+    score: u32,
+    ticks: u32,
+    crash: Option<Crash>, // none when the run hit MAX_TICKS
+}
+
+#[derive(Resource, Default)]
+struct HeadlessRuns {
+    ticks: u32, // of the current run
+    stats: Vec<RunStats>,
+}
+
+// Lets a bot fly `runs` runs on consecutive seeds without a window, one tick per update, and prints
+// the stats of every run as JSON
+pub fn run(args: Args, runs: u32, settings: GameSettings) {
+    let seed = args.seed.unwrap_or(0);
+    let tile_layouts = TileLayouts::read().unwrap_or_else(|err| {
+        eprintln!("Error: could not read the tile layouts: {err}");
+        process::exit(1);
+    });
+    let catalogue = CollidableCatalogue::read().unwrap_or_else(|err| {
+        eprintln!("Error: could not read the collidable catalogue: {err}");
+        process::exit(1);
+    });
+    let difficulty = Difficulty::read().unwrap_or_else(|err| {
+        eprintln!("Error: could not read the difficulty curve: {err}");
+        process::exit(1);
+    });
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(SimulationPlugin)
+        .insert_resource(Args {
+            seed: Some(seed),
+            ..args
+        })
+        .insert_resource(InputSource::Live {
+            recording: Replay::new(seed, settings),
+        })
+        .insert_resource(settings)
+        .insert_resource(tile_layouts)
+        .insert_resource(catalogue)
+        .insert_resource(World::new(seed, difficulty.clone()))
+        .insert_resource(difficulty)
+        .init_resource::<HeadlessRuns>()
+        .add_system(record_run.in_schedule(OnEnter(GameState::GameOver)))
+        .add_system(start_run.in_set(OnUpdate(GameState::MainMenu)))
+        .add_system(start_run.in_set(OnUpdate(GameState::GameOver)))
+        .add_systems(
+            (fly_bot.before(replay_tick), end_long_run)
+                .distributive_run_if(in_state(GameState::Playing))
+                .distributive_run_if(run_going)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+
+    // Advance the clock by exactly one tick every update, however fast the machine is
+    let period = app.world.resource::<FixedTime>().period;
+    let mut now = Instant::now();
+    while app.world.resource::<HeadlessRuns>().stats.len() < runs as usize {
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(now));
+        app.update();
+        now += period;
+    }
+
+    let stats = &app.world.resource::<HeadlessRuns>().stats;
+    println!("{}", serde_json::to_string_pretty(stats).unwrap());
+}
+
+fn start_run(mut next_state: ResMut<NextState<GameState>>) {
+    next_state.set(GameState::Playing);
+}
+
+// Keeps the stork at cruising height and speed, and drops the bundle when it would land on a waiting roof
+fn fly_bot(
+    mut input: ResMut<StorkInput>,
+    flight_model: Res<FlightModel>,
+    stork_query: Query<&Stork>,
+    target_query: Query<(&Transform, &Collidable), With<DeliveryTarget>>,
+) {
+    let stork = stork_query.get_single().unwrap();
+    let target_direction =
+        90.0 + ((CRUISE_HEIGHT - stork.position.y) * 10.0).clamp(-MAX_CLIMB_ANGLE, MAX_CLIMB_ANGLE);
+    input.controls = FlightControls {
+        thrust: stork.speed() < CRUISE_SPEED,
+        pitch_up: stork.direction() < target_direction - 2.0,
+        pitch_down: stork.direction() > target_direction + 2.0,
+    };
+
+    // The bundle keeps the velocity of the stork and falls without air resistance
+    let velocity = stork.velocity();
+    let gravity = flight_model.gravity;
+    input.drop_bundle = stork.has_bundle
+        && target_query.iter().any(|(transform, collidable)| {
+            let center = collidable.hitbox_center(transform).truncate() / PIXELS_PER_METER;
+            let half_size = collidable.size / PIXELS_PER_METER / 2.0;
+            let height = stork.position.y - (center.y + half_size.y);
+            if height <= 0.0 {
+                return false;
+            }
+            let fall_time =
+                (velocity.y + (velocity.y.powi(2) + 2.0 * gravity * height).sqrt()) / gravity;
+            let landing_x = stork.position.x + velocity.x * fall_time;
+            (landing_x - center.x).abs() < half_size.x
+        });
+}
+
+fn end_long_run(
+    mut headless_runs: ResMut<HeadlessRuns>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut run_outcome: ResMut<RunOutcome>,
+) {
+    headless_runs.ticks += 1;
+    if headless_runs.ticks >= MAX_TICKS {
+        run_outcome.end(None, &mut next_state);
+    }
+}
+
+// Runs when a run ends. The next run flies the next seed.
+fn record_run(
+    mut headless_runs: ResMut<HeadlessRuns>,
+    mut args: ResMut<Args>,
+    world: Res<World>,
+    score: Res<Score>,
+    deliveries: Res<Deliveries>,
+    run_outcome: Res<RunOutcome>,
+) {
+    let ticks = mem::take(&mut headless_runs.ticks);
+    headless_runs.stats.push(RunStats {
+        seed: world.seed(),
+        distance: score.distance,
+        deliveries: deliveries.count,
+        score: score.points(),
+        ticks,
+        crash: run_outcome.crash.clone(),
+    });
+    args.seed = Some(world.seed().wrapping_add(1));
+}
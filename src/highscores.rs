@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    data_dir,
+    delivery::Deliveries,
+    score::Score,
+    settings::{Assists, GameSettings, Preset},
+    world::World,
+};
+
+const HIGH_SCORE_COUNT: usize = 10;
+// Bump this when HighScore changes in a way that old files can't be read any more. Files with another version
+// are not loaded. New fields need `#[serde(default)]` instead, so the files from before them still load.
+const FILE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HighScore {
+    pub score: u32,
+    pub distance: f32, // in meters
+    pub deliveries: u32,
+    pub seed: u64,
+    pub timestamp: u64, // seconds since the unix epoch
+    // Runs from before there were presets were flown on Normal, without assists
+    #[serde(default)]
+    pub preset: Preset,
+    #[serde(default)]
+    pub assists: Assists,
+}
+
+#[derive(Serialize, Deserialize)]
+struct HighScoreFile {
+    version: u32,
+    entries: Vec<HighScore>,
+}
+
+// The best runs, sorted from high to low. Loaded at startup and saved after every run.
+#[derive(Resource, Default)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>,
+    pub last_rank: Option<usize>, // The place of the last run in the table, if it made it
+    path: Option<PathBuf>,
+}
+
+impl HighScores {
+    // Loads the table from the data directory. A missing, unreadable or outdated file gives an empty table.
+    pub fn load() -> Self {
+        let path = data_dir().map(|dir| dir.join("highscores.ron"));
+        let entries = match &path {
+            Some(path) if path.exists() => Self::read(path).unwrap_or_else(|err| {
+                warn!("Could not load high scores from {}: {err}", path.display());
+                // Keep the broken file around instead of overwriting it with the next save
+                let _ = fs::rename(path, path.with_extension("ron.corrupt"));
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        Self {
+            entries,
+            last_rank: None,
+            path,
+        }
+    }
+
+    fn read(path: &PathBuf) -> Result<Vec<HighScore>, String> {
+        Self::parse(&fs::read_to_string(path).map_err(|err| err.to_string())?)
+    }
+
+    fn parse(contents: &str) -> Result<Vec<HighScore>, String> {
+        let file: HighScoreFile = ron::from_str(contents).map_err(|err| err.to_string())?;
+        if file.version != FILE_VERSION {
+            return Err(format!("unsupported version {}", file.version));
+        }
+        Ok(file.entries)
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = HighScoreFile {
+            version: FILE_VERSION,
+            entries: self.entries.clone(),
+        };
+        let contents =
+            ron::ser::to_string_pretty(&file, Default::default()).map_err(|err| err.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, contents).map_err(|err| err.to_string())
+    }
+
+    // Adds the run to the table if it is good enough, and returns its place
+    fn insert(&mut self, high_score: HighScore) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| entry.score < high_score.score)
+            .unwrap_or(self.entries.len());
+        if rank >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        self.entries.insert(rank, high_score);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        Some(rank)
+    }
+}
+
+impl HighScore {
+    pub fn settings(&self) -> GameSettings {
+        GameSettings {
+            preset: self.preset,
+            assists: self.assists,
+        }
+    }
+
+    // The day the run was played, as yyyy-mm-dd
+    pub fn date(&self) -> String {
+        // Converts days since the epoch to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+        let days = (self.timestamp / 86_400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        format!("{year:04}-{month:02}-{day:02}")
+    }
+}
+
+pub fn record_high_score(
+    mut high_scores: ResMut<HighScores>,
+    score: Res<Score>,
+    deliveries: Res<Deliveries>,
+    world: Res<World>,
+    settings: Res<GameSettings>,
+) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    high_scores.last_rank = high_scores.insert(HighScore {
+        score: score.points(),
+        distance: score.distance,
+        deliveries: deliveries.count,
+        seed: world.seed(),
+        timestamp,
+        preset: settings.preset,
+        assists: settings.assists,
+    });
+    if high_scores.last_rank.is_some() {
+        if let Err(err) = high_scores.save() {
+            warn!("Could not save high scores: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_from_before_the_presets_were_flown_on_normal_without_assists() {
+        let old = "(version: 1, entries: [(score: 420, distance: 320.5, deliveries: 1, seed: 7, timestamp: 0)])";
+        let entries = HighScores::parse(old).unwrap();
+        assert_eq!(entries[0].score, 420);
+        assert_eq!(entries[0].preset, Preset::Normal);
+        assert_eq!(entries[0].assists, Assists::default());
+
+        let file = HighScoreFile {
+            version: FILE_VERSION,
+            entries: vec![HighScore {
+                preset: Preset::Easy,
+                assists: Preset::Easy.assists(),
+                ..entries[0].clone()
+            }],
+        };
+        let saved = ron::to_string(&file).unwrap();
+        assert_eq!(
+            HighScores::parse(&saved).unwrap()[0].settings(),
+            GameSettings::new(Preset::Easy)
+        );
+    }
+}
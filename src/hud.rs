@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+
+use crate::{
+    delivery::Deliveries, flight::FlightModel, game_state::GameState, ghost::GhostRace,
+    score::Score, settings::GameSettings, stork::Stork, world::World, FONT,
+};
+
+// Shows the state of the run in the top left corner while playing
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_hud.in_schedule(OnExit(GameState::MainMenu)))
+            .add_system(despawn_hud.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_systems((update_hud, update_stall_warning).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct Hud;
+
+// Each line of the HUD shows one value
+#[derive(Component, Clone, Copy)]
+enum HudField {
+    Distance,
+    Speed,
+    Heading,
+    Wind,
+    Weather,
+    Score,
+    Deliveries,
+    Ghost,
+}
+
+const HUD_FIELDS: [HudField; 8] = [
+    HudField::Distance,
+    HudField::Speed,
+    HudField::Heading,
+    HudField::Wind,
+    HudField::Weather,
+    HudField::Score,
+    HudField::Deliveries,
+    HudField::Ghost,
+];
+const TEXT_SIZE: f32 = 24.0;
+
+// The stamina bar under the text, filled as far as the stork has stamina left, red while it is exhausted
+#[derive(Component)]
+struct StaminaBar;
+
+const STAMINA_BAR_SIZE: Size = Size::new(Val::Px(200.0), Val::Px(12.0));
+const STAMINA_BAR_BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.5);
+const STAMINA_COLOR: Color = Color::rgb(0.3, 0.8, 0.3);
+const EXHAUSTED_COLOR: Color = Color::rgb(0.9, 0.3, 0.2);
+
+// Under the stamina bar, with the stall warning assist, while the stork is about to stall
+#[derive(Component)]
+struct StallWarning;
+
+// The warning shows below this part above the stall speed
+const STALL_WARNING_MARGIN: f32 = 1.3;
+const WARNING_SIZE: f32 = 32.0;
+const WARNING_COLOR: Color = Color::rgb(1.0, 0.8, 0.2);
+
+fn spawn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = asset_server.load(FONT);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        left: Val::Px(10.0),
+                        top: Val::Px(10.0),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+            Hud,
+        ))
+        .with_children(|parent| {
+            for field in HUD_FIELDS {
+                parent.spawn((
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: TEXT_SIZE,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    field,
+                ));
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: STAMINA_BAR_SIZE,
+                        margin: UiRect::top(Val::Px(4.0)),
+                        ..default()
+                    },
+                    background_color: STAMINA_BAR_BACKGROUND.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                                ..default()
+                            },
+                            background_color: STAMINA_COLOR.into(),
+                            ..default()
+                        },
+                        StaminaBar,
+                    ));
+                });
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: WARNING_SIZE,
+                        color: WARNING_COLOR,
+                    },
+                ),
+                StallWarning,
+            ));
+        });
+}
+
+fn despawn_hud(mut commands: Commands, hud_query: Query<Entity, With<Hud>>) {
+    for entity in hud_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_hud(
+    stork_query: Query<&Stork>,
+    score: Res<Score>,
+    deliveries: Res<Deliveries>,
+    ghost_race: Res<GhostRace>,
+    mut world: ResMut<World>,
+    mut text_query: Query<(&HudField, &mut Text)>,
+    mut bar_query: Query<(&mut Style, &mut BackgroundColor), With<StaminaBar>>,
+) {
+    let stork = stork_query.get_single().unwrap();
+    for (field, mut text) in text_query.iter_mut() {
+        text.sections[0].value = match field {
+            HudField::Distance => format!("Distance: {:.0} m", stork.position.x),
+            // The speed through the air, which is what keeps the stork flying
+            HudField::Speed => format!("Airspeed: {:.1} m/s", stork.speed()),
+            // The angle above the horizon, in the direction the stork is flying
+            HudField::Heading => format!("Heading: {:+.0}°", stork.direction().abs() - 90.0),
+            HudField::Wind => format!(
+                "Wind: {:+.1} m/s, rising {:+.1} m/s",
+                stork.wind.x, stork.wind.y
+            ),
+            HudField::Weather => {
+                format!("Weather: {}", world.conditions(stork.position.x).describe())
+            }
+            HudField::Score => format!("Score: {}", score.points()),
+            HudField::Deliveries => format!("Deliveries: {}", deliveries.count),
+            HudField::Ghost => match ghost_race.lead(stork) {
+                Some(lead) if lead >= 0.0 => format!("Ghost: {lead:.0} m behind you"),
+                Some(lead) => format!("Ghost: {:.0} m ahead of you", -lead),
+                None => String::new(),
+            },
+        };
+    }
+    for (mut style, mut color) in bar_query.iter_mut() {
+        style.size.width = Val::Percent(stork.stamina.level * 100.0);
+        *color = if stork.stamina.exhausted {
+            EXHAUSTED_COLOR
+        } else {
+            STAMINA_COLOR
+        }
+        .into();
+    }
+}
+
+fn update_stall_warning(
+    settings: Res<GameSettings>,
+    flight_model: Res<FlightModel>,
+    stork_query: Query<&Stork>,
+    mut text_query: Query<&mut Text, With<StallWarning>>,
+) {
+    let stork = stork_query.get_single().unwrap();
+    let standing = stork.on_ground || stork.perch.is_some();
+    let stalling = settings.assists.stall_warning
+        && !standing
+        && stork.speed() < flight_model.stall_speed * STALL_WARNING_MARGIN;
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = if stalling { "Stall!" } else { "" }.to_string();
+    }
+}
@@ -0,0 +1,109 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::prelude::*;
+
+use std::f32::consts::TAU;
+
+use crate::{
+    game_state::GameState,
+    world::{collidables::Collidable, wind::Wind, World},
+    PIXELS_PER_METER,
+};
+
+// Leaves that drift with the wind, so the player can see where the air moves.
+// They are only for show, so they use their own randomness and don't touch the simulation.
+pub struct LeavesPlugin;
+
+impl Plugin for LeavesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(despawn_leaves.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(despawn_leaves.in_schedule(OnExit(GameState::GameOver)))
+            .add_systems((spawn_leaves, drift_leaves).in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+#[derive(Component)]
+struct Leaf {
+    age: f32,  // in seconds
+    spin: f32, // in radians per second
+}
+
+const LEAVES_PER_SECOND: f32 = 15.0;
+const LEAF_LIFETIME: f32 = 5.0; // in seconds
+const LEAF_SIZE: Vec2 = Vec2 { x: 8.0, y: 4.0 }; // in pixels
+const LEAF_FALL_SPEED: f32 = 0.3; // in still air, in meters per second
+const MAX_SPIN: f32 = 4.0; // in radians per second
+const LEAF_COLORS: [Color; 3] = [
+    Color::rgb(0.45, 0.6, 0.2),
+    Color::rgb(0.75, 0.55, 0.15),
+    Color::rgb(0.6, 0.3, 0.1),
+];
+
+// Spawns leaves anywhere in view, about as many every second
+fn spawn_leaves(
+    mut commands: Commands,
+    time: Res<Time>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (Ok(camera), Ok(window)) = (camera_query.get_single(), window_query.get_single()) else {
+        return;
+    };
+    let mut rng = thread_rng();
+    let expected = LEAVES_PER_SECOND * time.delta_seconds();
+    let count = expected as usize + usize::from(rng.gen::<f32>() < expected.fract());
+    let half_view = Vec2::new(window.width(), window.height()) * camera.scale.truncate() / 2.0;
+    for _ in 0..count {
+        let position = camera.translation.truncate()
+            + Vec2::new(
+                rng.gen_range(-half_view.x..half_view.x),
+                rng.gen_range(-half_view.y..half_view.y),
+            );
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: *LEAF_COLORS.choose(&mut rng).unwrap(),
+                    custom_size: Some(LEAF_SIZE),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(5.0))
+                    .with_rotation(Quat::from_rotation_z(rng.gen_range(0.0..TAU))),
+                ..default()
+            },
+            Leaf {
+                age: 0.0,
+                spin: rng.gen_range(-MAX_SPIN..MAX_SPIN),
+            },
+        ));
+    }
+}
+
+// Moves every leaf with the air around it, and fades it out at the end of its life
+fn drift_leaves(
+    mut commands: Commands,
+    time: Res<Time>,
+    wind: Res<Wind>,
+    mut world: ResMut<World>,
+    collidable_query: Query<(&Collidable, &Transform), Without<Leaf>>,
+    mut leaf_query: Query<(Entity, &mut Leaf, &mut Transform, &mut Sprite)>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut leaf, mut transform, mut sprite) in leaf_query.iter_mut() {
+        leaf.age += dt;
+        if leaf.age > LEAF_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let position = transform.translation.truncate() / PIXELS_PER_METER;
+        let air = wind.at(&mut world, position, collidable_query.iter());
+        let velocity = air - Vec2::new(0.0, LEAF_FALL_SPEED);
+        transform.translation += (velocity * dt * PIXELS_PER_METER).extend(0.0);
+        transform.rotate_z(leaf.spin * dt);
+        sprite.color.set_a(1.0 - leaf.age / LEAF_LIFETIME);
+    }
+}
+
+fn despawn_leaves(mut commands: Commands, leaf_query: Query<Entity, With<Leaf>>) {
+    for entity in leaf_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -1,28 +1,163 @@
+mod args;
 mod camera;
+mod debug;
+mod delivery;
+mod flight;
+mod game_state;
+mod ghost;
+mod headless;
+mod highscores;
+mod hud;
+mod leaves;
+mod menu;
+mod replay;
+mod score;
+mod settings;
+mod shapes;
+mod simulation;
+mod sky;
 mod stork;
+mod tick;
 mod world;
 
-use std::env;
+use std::{env, fs, path::PathBuf, process};
 
-use bevy::prelude::*;
+use args::{fixed_seed, Args};
+use bevy::{asset::FileAssetIo, prelude::*};
 use camera::*;
-use stork::{move_stork, spawn_stork};
-use world::tiles::spawn_tiles;
+use debug::DebugPlugin;
+use delivery::drop_bundle;
+use game_state::{no_stork, run_going, GameState};
+use ghost::{ghost_tick, save_ghost, start_ghost_race, GhostRace};
+use highscores::{record_high_score, HighScores};
+use hud::HudPlugin;
+use leaves::LeavesPlugin;
+use menu::*;
+use replay::{live_input, save_last_replay, InputSource, Replay};
+use settings::GameSettings;
+use simulation::SimulationPlugin;
+use sky::SkyPlugin;
+use stork::{blink_invulnerable_stork, move_stork, read_stork_input};
+use tick::interpolate_transforms;
+use world::{catalogue::CataloguePlugin, difficulty::DifficultyPlugin, layouts::TileLayoutPlugin};
 
 const PIXELS_PER_METER: f32 = 100.0;
+const FONT: &str = "fonts/FiraSans-Bold.ttf";
+
+// Where high scores and replays are kept, if the platform has a data directory
+fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("delivery"))
+}
+
+// The texture at `path`, or the default texture when there is no asset server, like in a headless run
+fn load_texture(asset_server: Option<&AssetServer>, path: &str) -> Handle<Image> {
+    asset_server.map_or_else(Handle::default, |asset_server| asset_server.load(path))
+}
+
+// Reads a file from the asset folder directly, like the asset server would find it
+fn read_asset(path: &str) -> Result<Vec<u8>, String> {
+    let path = FileAssetIo::get_base_path().join("assets").join(path);
+    fs::read(&path).map_err(|err| format!("{}: {err}", path.display()))
+}
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
+    let mut args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
+    let mut settings = GameSettings::new(args.preset.unwrap_or_default());
+    let input_source = match &args.replay {
+        Some(path) => {
+            let replay = Replay::load(path).unwrap_or_else(|err| {
+                eprintln!("Error: could not load replay {}: {err}", path.display());
+                process::exit(1);
+            });
+            // A replay is only the same run in the world and with the settings it was recorded with
+            args.seed = Some(replay.seed);
+            settings = replay.settings;
+            InputSource::Playback { replay, tick: 0 }
+        }
+        None => InputSource::Live {
+            recording: Replay::new(0, settings),
+        },
+    };
+    if let Some(runs) = args.headless {
+        headless::run(args, runs, settings);
+        return;
+    }
     App::new()
-        .add_plugins(DefaultPlugins)
+        .add_plugins(DefaultPlugins.set(AssetPlugin {
+            watch_for_changes: true,
+            ..default()
+        }))
+        .add_plugin(SimulationPlugin)
+        .add_plugin(TileLayoutPlugin)
+        .add_plugin(CataloguePlugin)
+        .add_plugin(DifficultyPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(LeavesPlugin)
+        .add_plugin(SkyPlugin)
+        .add_plugin(DebugPlugin)
         // Resources
-        .init_resource::<world::World>()
+        .init_resource::<GhostRace>()
+        .insert_resource(HighScores::load())
+        .insert_resource(args)
+        .insert_resource(settings)
+        .insert_resource(input_source)
         // Startup Systems
         .add_startup_system(spawn_camera)
-        .add_startup_system(spawn_stork)
+        // State transitions
+        .add_system(spawn_main_menu.in_schedule(OnEnter(GameState::MainMenu)))
+        .add_system(despawn_menu.in_schedule(OnExit(GameState::MainMenu)))
+        .add_system(
+            start_ghost_race
+                .run_if(no_stork)
+                .in_schedule(OnEnter(GameState::Playing)),
+        )
+        .add_system(spawn_pause_menu.in_schedule(OnEnter(GameState::Paused)))
+        .add_system(despawn_menu.in_schedule(OnExit(GameState::Paused)))
+        .add_systems(
+            (
+                record_high_score.run_if(live_input),
+                save_ghost.run_if(live_input).run_if(fixed_seed),
+                save_last_replay,
+                spawn_game_over_menu,
+            )
+                .chain()
+                .in_schedule(OnEnter(GameState::GameOver)),
+        )
+        .add_system(despawn_menu.in_schedule(OnExit(GameState::GameOver)))
         // Systems
-        .add_system(move_camera)
-        .add_system(move_stork)
-        .add_system(spawn_tiles)
+        .add_systems(
+            (
+                main_menu_input,
+                choose_settings.run_if(live_input),
+                respawn_main_menu.run_if(resource_changed::<GameSettings>()),
+            )
+                .chain()
+                .in_set(OnUpdate(GameState::MainMenu)),
+        )
+        .add_system(pause_menu_input.in_set(OnUpdate(GameState::Paused)))
+        .add_system(game_over_menu_input.in_set(OnUpdate(GameState::GameOver)))
+        .add_systems(
+            (
+                playing_input,
+                read_stork_input.run_if(live_input),
+                blink_invulnerable_stork,
+                move_camera,
+                interpolate_transforms,
+            )
+                .in_set(OnUpdate(GameState::Playing)),
+        )
+        // The ghost follows the stork within the simulation tick
+        .add_system(
+            ghost_tick
+                .after(move_stork)
+                .before(drop_bundle)
+                .run_if(in_state(GameState::Playing))
+                .run_if(run_going)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
         .run();
 }
@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+
+use crate::{
+    delivery::Deliveries,
+    game_state::GameState,
+    highscores::HighScores,
+    replay::InputSource,
+    score::Score,
+    settings::GameSettings,
+    world::{catalogue::CollidableCatalogue, difficulty::Difficulty, layouts::TileLayouts, World},
+    FONT,
+};
+
+// Marker for the root node of a menu screen, so it can be despawned when the state is left
+#[derive(Component)]
+pub struct Menu;
+
+const TITLE_SIZE: f32 = 80.0;
+const TEXT_SIZE: f32 = 30.0;
+
+// Spawns a full screen, centered column of text lines. The first line is the title.
+fn spawn_menu(commands: &mut Commands, asset_server: &AssetServer, title: &str, lines: &[String]) {
+    let font = asset_server.load(FONT);
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.5).into(),
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font: font.clone(),
+                    font_size: TITLE_SIZE,
+                    color: Color::WHITE,
+                },
+            ));
+            for line in lines {
+                parent.spawn(TextBundle::from_section(
+                    line,
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: TEXT_SIZE,
+                        color: Color::WHITE,
+                    },
+                ));
+            }
+        });
+}
+
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+) {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        "Stork Delivery",
+        &[
+            "Press Enter to start".to_string(),
+            "Shift: flap   Up/Down: steer   Space: drop bundle   Esc: pause".to_string(),
+            format!("Left/Right: difficulty   < {} >", settings.preset.name()),
+            format!(
+                "A: auto-level {}   W: stall warning {}   I: invulnerability {}",
+                on_off(settings.assists.auto_level),
+                on_off(settings.assists.stall_warning),
+                on_off(settings.assists.invulnerability)
+            ),
+        ],
+    );
+}
+
+// Shows the main menu again with the settings that were just changed
+pub fn respawn_main_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+    menu_query: Query<Entity, With<Menu>>,
+) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    spawn_main_menu(commands, asset_server, settings);
+}
+
+pub fn spawn_pause_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        "Paused",
+        &[
+            "Press Esc to continue".to_string(),
+            "Press Q to quit to the main menu".to_string(),
+        ],
+    );
+}
+
+pub fn spawn_game_over_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    deliveries: Res<Deliveries>,
+    score: Res<Score>,
+    world: Res<World>,
+    high_scores: Res<HighScores>,
+    settings: Res<GameSettings>,
+) {
+    let mut lines = vec![
+        format!("Score: {}", score.points()),
+        format!("Deliveries: {}", deliveries.count),
+        format!("Seed: {}", world.seed()),
+        format!("Difficulty: {}", settings.describe()),
+    ];
+    if let Some(rank) = high_scores.last_rank {
+        lines.push(format!("New high score! Place {}", rank + 1));
+    }
+    lines.push("High scores".to_string());
+    for (rank, entry) in high_scores.entries.iter().enumerate() {
+        lines.push(format!(
+            "{}. {}   {:.0} m   {} deliveries   seed {}   {}   {}",
+            rank + 1,
+            entry.score,
+            entry.distance,
+            entry.deliveries,
+            entry.seed,
+            entry.settings().describe(),
+            entry.date()
+        ));
+    }
+    lines.push("Press Enter to restart".to_string());
+    lines.push("Press Esc to return to the main menu".to_string());
+    spawn_menu(&mut commands, &asset_server, "Game Over", &lines);
+}
+
+pub fn despawn_menu(mut commands: Commands, menu_query: Query<Entity, With<Menu>>) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn main_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    input_source: Res<InputSource>,
+    tile_layouts: Res<TileLayouts>,
+    catalogue: Option<Res<CollidableCatalogue>>,
+    difficulty: Res<Difficulty>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    // A run can't start before the world can be built
+    if !tile_layouts.loaded() || catalogue.is_none() || !difficulty.loaded() {
+        return;
+    }
+    // A replay starts by itself
+    let playback = matches!(*input_source, InputSource::Playback { .. });
+    if keyboard_input.just_pressed(KeyCode::Return) || playback {
+        next_state.set(GameState::Playing);
+    }
+}
+
+// Picks the difficulty for the next runs. A new preset comes with its own assists, which can then be
+// switched on and off one by one.
+pub fn choose_settings(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<GameSettings>) {
+    let steps = isize::from(keyboard_input.just_pressed(KeyCode::Right))
+        - isize::from(keyboard_input.just_pressed(KeyCode::Left));
+    if steps != 0 {
+        *settings = GameSettings::new(settings.preset.cycle(steps));
+    }
+    if keyboard_input.just_pressed(KeyCode::A) {
+        settings.assists.auto_level ^= true;
+    }
+    if keyboard_input.just_pressed(KeyCode::W) {
+        settings.assists.stall_warning ^= true;
+    }
+    if keyboard_input.just_pressed(KeyCode::I) {
+        settings.assists.invulnerability ^= true;
+    }
+}
+
+pub fn playing_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Paused);
+    }
+}
+
+pub fn pause_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::Q) {
+        next_state.set(GameState::MainMenu);
+    }
+}
+
+pub fn game_over_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        next_state.set(GameState::Playing);
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_state.set(GameState::MainMenu);
+    }
+}
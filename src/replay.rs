@@ -0,0 +1,352 @@
+use bevy::prelude::*;
+
+use std::{fs, iter, path::Path};
+
+use crate::{
+    data_dir,
+    flight::FlightControls,
+    game_state::{GameState, RunOutcome},
+    settings::{Assists, GameSettings, Preset},
+    stork::StorkInput,
+    world::World,
+};
+
+// The input of every tick of a run. Together with the seed and the settings, this is enough to play the run again.
+pub struct Replay {
+    pub version: String, // the version of the game that recorded it
+    pub seed: u64,
+    pub settings: GameSettings,
+    inputs: Vec<u8>, // one bit mask per tick, see `to_mask`
+}
+
+// Where the stork input comes from during a run
+#[derive(Resource)]
+pub enum InputSource {
+    // The keyboard, while recording every tick
+    Live { recording: Replay },
+    // A replay file, without any live input
+    Playback { replay: Replay, tick: usize },
+}
+
+const MAGIC: &[u8; 4] = b"DLVR";
+// Bump this when the layout of the file changes, and keep decoding the older layouts
+const FORMAT_VERSION: u8 = 2;
+
+const THRUST: u8 = 1 << 0;
+const PITCH_UP: u8 = 1 << 1;
+const PITCH_DOWN: u8 = 1 << 2;
+const DROP_BUNDLE: u8 = 1 << 3;
+
+const AUTO_LEVEL: u8 = 1 << 0;
+const STALL_WARNING: u8 = 1 << 1;
+const INVULNERABILITY: u8 = 1 << 2;
+
+fn to_mask(input: &StorkInput) -> u8 {
+    let mut mask = 0;
+    for (pressed, bit) in [
+        (input.controls.thrust, THRUST),
+        (input.controls.pitch_up, PITCH_UP),
+        (input.controls.pitch_down, PITCH_DOWN),
+        (input.drop_bundle, DROP_BUNDLE),
+    ] {
+        if pressed {
+            mask |= bit;
+        }
+    }
+    mask
+}
+
+fn from_mask(mask: u8) -> StorkInput {
+    StorkInput {
+        controls: FlightControls {
+            thrust: mask & THRUST != 0,
+            pitch_up: mask & PITCH_UP != 0,
+            pitch_down: mask & PITCH_DOWN != 0,
+        },
+        drop_bundle: mask & DROP_BUNDLE != 0,
+    }
+}
+
+// The settings as two bytes: the preset, and a bit mask of the assists
+fn encode_settings(settings: &GameSettings) -> [u8; 2] {
+    let preset = Preset::ALL
+        .iter()
+        .position(|&preset| preset == settings.preset)
+        .unwrap() as u8;
+    let mut assists = 0;
+    for (on, bit) in [
+        (settings.assists.auto_level, AUTO_LEVEL),
+        (settings.assists.stall_warning, STALL_WARNING),
+        (settings.assists.invulnerability, INVULNERABILITY),
+    ] {
+        if on {
+            assists |= bit;
+        }
+    }
+    [preset, assists]
+}
+
+fn decode_settings(bytes: &[u8]) -> Result<GameSettings, String> {
+    let preset = *Preset::ALL
+        .get(bytes[0] as usize)
+        .ok_or_else(|| format!("unknown preset {}", bytes[0]))?;
+    Ok(GameSettings {
+        preset,
+        assists: Assists {
+            auto_level: bytes[1] & AUTO_LEVEL != 0,
+            stall_warning: bytes[1] & STALL_WARNING != 0,
+            invulnerability: bytes[1] & INVULNERABILITY != 0,
+        },
+    })
+}
+
+// Takes the next `n` bytes from the front of `bytes`
+pub fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Result<&'a [u8], String> {
+    if bytes.len() < n {
+        return Err("unexpected end of file".to_string());
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Ok(head)
+}
+
+// Takes the next `N` bytes from the front of `bytes`, for reading a number
+pub fn take_array<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], String> {
+    let mut array = [0; N];
+    array.copy_from_slice(take(bytes, N)?);
+    Ok(array)
+}
+
+impl Replay {
+    pub fn new(seed: u64, settings: GameSettings) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            seed,
+            settings,
+            inputs: Vec::new(),
+        }
+    }
+
+    // The file is a small header followed by the inputs, run-length encoded as (mask, number of ticks) pairs
+    pub fn encode(&self) -> Vec<u8> {
+        let mut runs: Vec<(u8, u16)> = Vec::new();
+        for &mask in &self.inputs {
+            match runs.last_mut() {
+                Some((run_mask, count)) if *run_mask == mask && *count < u16::MAX => *count += 1,
+                _ => runs.push((mask, 1)),
+            }
+        }
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.version.len() as u8);
+        bytes.extend(self.version.as_bytes());
+        bytes.extend(self.seed.to_le_bytes());
+        bytes.extend(encode_settings(&self.settings));
+        bytes.extend((runs.len() as u32).to_le_bytes());
+        for (mask, count) in runs {
+            bytes.push(mask);
+            bytes.extend(count.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn decode(mut bytes: &[u8]) -> Result<Self, String> {
+        let bytes = &mut bytes;
+        if take(bytes, MAGIC.len())? != MAGIC {
+            return Err("not a replay file".to_string());
+        }
+        let format_version = take(bytes, 1)?[0];
+        if !(1..=FORMAT_VERSION).contains(&format_version) {
+            return Err(format!("unsupported replay format {format_version}"));
+        }
+        let version_len = take(bytes, 1)?[0] as usize;
+        let version =
+            String::from_utf8(take(bytes, version_len)?.to_vec()).map_err(|err| err.to_string())?;
+        let seed = u64::from_le_bytes(take_array(bytes)?);
+        // Format 1 is from before there were presets, when every run was flown on Normal without assists
+        let settings = if format_version == 1 {
+            GameSettings {
+                preset: Preset::Normal,
+                assists: Assists::default(),
+            }
+        } else {
+            decode_settings(take(bytes, 2)?)?
+        };
+        let run_count = u32::from_le_bytes(take_array(bytes)?);
+        let mut inputs = Vec::new();
+        for _ in 0..run_count {
+            let mask = take(bytes, 1)?[0];
+            let count = u16::from_le_bytes(take_array(bytes)?);
+            inputs.extend(iter::repeat_n(mask, count as usize));
+        }
+        Ok(Self {
+            version,
+            seed,
+            settings,
+            inputs,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|err| err.to_string())?;
+        let replay = Self::decode(&bytes)?;
+        if replay.version != env!("CARGO_PKG_VERSION") {
+            warn!(
+                "{} was recorded with version {}, it may play differently in this version",
+                path.display(),
+                replay.version
+            );
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, self.encode()).map_err(|err| err.to_string())
+    }
+}
+
+pub fn live_input(input_source: Res<InputSource>) -> bool {
+    matches!(*input_source, InputSource::Live { .. })
+}
+
+// Runs when a run starts. A live run starts a new recording, a playback starts from the first tick.
+pub fn start_replay(
+    mut input_source: ResMut<InputSource>,
+    world: Res<World>,
+    settings: Res<GameSettings>,
+) {
+    match &mut *input_source {
+        InputSource::Live { recording } => *recording = Replay::new(world.seed(), *settings),
+        InputSource::Playback { tick, .. } => *tick = 0,
+    }
+}
+
+// Runs at the start of every tick, before anything reads the input
+pub fn replay_tick(
+    mut input_source: ResMut<InputSource>,
+    mut input: ResMut<StorkInput>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut run_outcome: ResMut<RunOutcome>,
+) {
+    match &mut *input_source {
+        InputSource::Live { recording } => recording.inputs.push(to_mask(&input)),
+        InputSource::Playback { replay, tick } => match replay.inputs.get(*tick) {
+            Some(&mask) => {
+                *input = from_mask(mask);
+                *tick += 1;
+            }
+            // The recorded run ended here
+            None => run_outcome.end(None, &mut next_state),
+        },
+    }
+}
+
+// Keeps the last live run, so it can be shared or played again with --replay
+pub fn save_last_replay(input_source: Res<InputSource>) {
+    let InputSource::Live { recording } = &*input_source else {
+        return;
+    };
+    let Some(path) = data_dir().map(|dir| dir.join("replays").join("last.replay")) else {
+        return;
+    };
+    match recording.save(&path) {
+        Ok(()) => info!("Saved the replay of this run to {}", path.display()),
+        Err(err) => warn!("Could not save the replay to {}: {err}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::tests::{fly, live, script};
+
+    fn recording() -> Replay {
+        let mut replay = Replay::new(
+            0xDEAD_BEEF,
+            GameSettings {
+                preset: Preset::Hard,
+                assists: Assists {
+                    auto_level: true,
+                    stall_warning: false,
+                    invulnerability: true,
+                },
+            },
+        );
+        // Single ticks, short runs, and a run too long for one count
+        replay
+            .inputs
+            .extend([0, THRUST, THRUST | PITCH_UP, PITCH_DOWN | DROP_BUNDLE]);
+        replay.inputs.extend(iter::repeat_n(THRUST, 5));
+        replay
+            .inputs
+            .extend(iter::repeat_n(0, u16::MAX as usize + 10));
+        replay.inputs.push(DROP_BUNDLE);
+        replay
+    }
+
+    #[test]
+    fn decoding_an_encoded_replay_gives_it_back() {
+        let replay = recording();
+        let decoded = Replay::decode(&replay.encode()).unwrap();
+        assert_eq!(decoded.version, replay.version);
+        assert_eq!(decoded.seed, replay.seed);
+        assert_eq!(decoded.settings, replay.settings);
+        assert_eq!(decoded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn broken_replays_are_rejected() {
+        let bytes = recording().encode();
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert!(Replay::decode(&bad_magic).is_err());
+        for format_version in [0, FORMAT_VERSION + 1] {
+            let mut other_version = bytes.clone();
+            other_version[MAGIC.len()] = format_version;
+            assert!(Replay::decode(&other_version).is_err(), "{format_version}");
+        }
+        for len in 0..bytes.len() {
+            assert!(Replay::decode(&bytes[..len]).is_err(), "{len}");
+        }
+    }
+
+    #[test]
+    fn replays_from_before_the_presets_are_flown_on_normal() {
+        let replay = recording();
+        let mut bytes = replay.encode();
+        // Format 1 has no settings between the seed and the inputs
+        bytes[MAGIC.len()] = 1;
+        let settings_at = MAGIC.len() + 2 + replay.version.len() + 8;
+        bytes.drain(settings_at..settings_at + 2);
+        let decoded = Replay::decode(&bytes).unwrap();
+        assert_eq!(decoded.seed, replay.seed);
+        assert_eq!(decoded.settings.preset, Preset::Normal);
+        assert_eq!(decoded.settings.assists, Assists::default());
+        assert_eq!(decoded.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn playing_a_recording_back_flies_the_same_run() {
+        let settings = GameSettings::new(Preset::Easy);
+        let recorded = fly(5, settings, live(), script(1200), 3);
+        let InputSource::Live { recording } = recorded.input_source else {
+            panic!("the recorded run was not live");
+        };
+        // The settings come from the replay, like when it is loaded from a file
+        let replay = Replay::decode(&recording.encode()).unwrap();
+        let played = fly(
+            replay.seed,
+            replay.settings,
+            InputSource::Playback { replay, tick: 0 },
+            Vec::new(),
+            1,
+        );
+        assert_eq!(played.trajectory, recorded.trajectory);
+        assert_eq!(played.distance, recorded.distance);
+        assert_eq!(played.deliveries, recorded.deliveries);
+        assert_eq!(played.crash, recorded.crash);
+    }
+}
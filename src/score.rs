@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+
+use crate::{delivery::Delivered, stork::Stork};
+
+const POINTS_PER_METER: f32 = 1.0;
+const POINTS_PER_DELIVERY: u32 = 100;
+
+#[derive(Resource, Default)]
+pub struct Score {
+    pub distance: f32, // the furthest the stork has flown, in meters
+    delivery_points: u32,
+}
+
+impl Score {
+    pub fn points(&self) -> u32 {
+        (self.distance * POINTS_PER_METER) as u32 + self.delivery_points
+    }
+}
+
+pub fn update_score(
+    mut score: ResMut<Score>,
+    stork_query: Query<&Stork>,
+    mut delivered_event_reader: EventReader<Delivered>,
+) {
+    let stork = stork_query.get_single().unwrap();
+    score.distance = score.distance.max(stork.position.x);
+    score.delivery_points += delivered_event_reader.iter().count() as u32 * POINTS_PER_DELIVERY;
+}
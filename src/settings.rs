@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::flight::FlightModel;
+
+// How forgiving the stork is to fly, chosen in the main menu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+// Help for the player on top of the preset. Every preset comes with its own, which can be changed in the menu.
+// High scores from before there were assists had none.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assists {
+    pub auto_level: bool, // without up or down pressed, the stork slowly levels out
+    pub stall_warning: bool, // the HUD warns when the stork is about to stall
+    pub invulnerability: bool, // a hit costs stamina instead of the run, and the stork can't be hit for a moment
+}
+
+// What the player chose for the runs. Replays and high scores keep it with the run.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GameSettings {
+    pub preset: Preset,
+    pub assists: Assists,
+}
+
+// How a preset changes the flight model, as factors of the defaults
+struct Scales {
+    gravity: f32,
+    stall_speed: f32,
+    turn_speed: f32,
+    air_resistance: f32,
+    hitbox: f32, // of the size of the stork, smaller is more forgiving
+}
+
+const EASY: Scales = Scales {
+    gravity: 0.8,
+    stall_speed: 0.75,
+    turn_speed: 1.2,
+    air_resistance: 0.7,
+    hitbox: 0.75,
+};
+const NORMAL: Scales = Scales {
+    gravity: 1.0,
+    stall_speed: 1.0,
+    turn_speed: 1.0,
+    air_resistance: 1.0,
+    hitbox: 1.0,
+};
+const HARD: Scales = Scales {
+    gravity: 1.2,
+    stall_speed: 1.25,
+    turn_speed: 0.85,
+    air_resistance: 1.3,
+    hitbox: 1.1,
+};
+
+const AUTO_LEVEL_SPEED: f32 = 40.0; // in degrees per second
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Easy, Preset::Normal, Preset::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Easy => "Easy",
+            Preset::Normal => "Normal",
+            Preset::Hard => "Hard",
+        }
+    }
+
+    // The preset `steps` places further in ALL, wrapping around
+    pub fn cycle(&self, steps: isize) -> Self {
+        let index = Self::ALL.iter().position(|preset| preset == self).unwrap() as isize;
+        Self::ALL[(index + steps).rem_euclid(Self::ALL.len() as isize) as usize]
+    }
+
+    pub fn assists(&self) -> Assists {
+        match self {
+            Preset::Easy => Assists {
+                auto_level: true,
+                stall_warning: true,
+                invulnerability: true,
+            },
+            Preset::Normal => Assists {
+                stall_warning: true,
+                ..default()
+            },
+            Preset::Hard => Assists::default(),
+        }
+    }
+
+    fn scales(&self) -> &'static Scales {
+        match self {
+            Preset::Easy => &EASY,
+            Preset::Normal => &NORMAL,
+            Preset::Hard => &HARD,
+        }
+    }
+
+    // How much of the size of the stork counts when it hits something
+    pub fn hitbox_scale(&self) -> f32 {
+        self.scales().hitbox
+    }
+}
+
+impl Assists {
+    // The names of the assists that are on
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.auto_level, "auto-level"),
+            (self.stall_warning, "stall warning"),
+            (self.invulnerability, "invulnerability"),
+        ]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect()
+    }
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self::new(Preset::default())
+    }
+}
+
+impl GameSettings {
+    // A preset with the assists it comes with
+    pub fn new(preset: Preset) -> Self {
+        Self {
+            preset,
+            assists: preset.assists(),
+        }
+    }
+
+    // The flight model that the stork flies with
+    pub fn flight_model(&self) -> FlightModel {
+        let default = FlightModel::default();
+        let scales = self.preset.scales();
+        FlightModel {
+            gravity: default.gravity * scales.gravity,
+            stall_speed: default.stall_speed * scales.stall_speed,
+            turn_speed: default.turn_speed * scales.turn_speed,
+            air_resistance: default.air_resistance * scales.air_resistance,
+            auto_level_speed: if self.assists.auto_level {
+                AUTO_LEVEL_SPEED
+            } else {
+                0.0
+            },
+            ..default
+        }
+    }
+
+    // Like "Easy (auto-level, stall warning)", or just the preset without assists
+    pub fn describe(&self) -> String {
+        let assists = self.assists.names();
+        if assists.is_empty() {
+            self.preset.name().to_string()
+        } else {
+            format!("{} ({})", self.preset.name(), assists.join(", "))
+        }
+    }
+}
+
+// Runs when a run starts, so the whole run flies with the settings it started with
+pub fn apply_settings(settings: Res<GameSettings>, mut flight_model: ResMut<FlightModel>) {
+    *flight_model = settings.flight_model();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_scale_the_flight_model_and_the_hitbox() {
+        let default = FlightModel::default();
+        // Gravity, stall speed, turn speed, air resistance and hitbox, as factors of Normal
+        for (preset, factors) in [
+            (Preset::Easy, [0.8, 0.75, 1.2, 0.7, 0.75]),
+            (Preset::Normal, [1.0; 5]),
+            (Preset::Hard, [1.2, 1.25, 0.85, 1.3, 1.1]),
+        ] {
+            let model = GameSettings::new(preset).flight_model();
+            assert_eq!(model.gravity, default.gravity * factors[0], "{preset:?}");
+            assert_eq!(
+                model.stall_speed,
+                default.stall_speed * factors[1],
+                "{preset:?}"
+            );
+            assert_eq!(
+                model.turn_speed,
+                default.turn_speed * factors[2],
+                "{preset:?}"
+            );
+            assert_eq!(
+                model.air_resistance,
+                default.air_resistance * factors[3],
+                "{preset:?}"
+            );
+            assert_eq!(preset.hitbox_scale(), factors[4], "{preset:?}");
+            // Everything else flies the same
+            assert_eq!(
+                model.max_acceleration, default.max_acceleration,
+                "{preset:?}"
+            );
+            assert_eq!(
+                model.max_landing_speed, default.max_landing_speed,
+                "{preset:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn auto_level_follows_the_assist() {
+        let mut settings = GameSettings::new(Preset::Hard);
+        assert_eq!(settings.flight_model().auto_level_speed, 0.0);
+        settings.assists.auto_level = true;
+        assert!(settings.flight_model().auto_level_speed > 0.0);
+    }
+}
@@ -0,0 +1,198 @@
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
+
+// Convex polygons, as their corners in order, and the separating axis test between them
+
+// The corners of a rectangle around `center`
+pub fn rectangle(center: Vec2, size: Vec2) -> Vec<Vec2> {
+    let half = size / 2.0;
+    vec![
+        center + Vec2::new(-half.x, -half.y),
+        center + Vec2::new(half.x, -half.y),
+        center + Vec2::new(half.x, half.y),
+        center + Vec2::new(-half.x, half.y),
+    ]
+}
+
+pub fn transformed(polygon: &[Vec2], transform: &Transform) -> Vec<Vec2> {
+    polygon
+        .iter()
+        .map(|point| transform.transform_point(point.extend(0.0)).truncate())
+        .collect()
+}
+
+// A polygon is convex when every turn along its corners goes the same way. Corners that all lie on a line
+// don't turn at all, and make no polygon.
+pub fn is_convex(polygon: &[Vec2]) -> bool {
+    let turns: Vec<f32> = (0..polygon.len())
+        .map(|i| {
+            let (a, b, c) = (
+                polygon[i],
+                polygon[(i + 1) % polygon.len()],
+                polygon[(i + 2) % polygon.len()],
+            );
+            (b - a).perp_dot(c - b)
+        })
+        .collect();
+    let left = turns.iter().all(|&t| t >= 0.0) && turns.iter().any(|&t| t > 0.0);
+    let right = turns.iter().all(|&t| t <= 0.0) && turns.iter().any(|&t| t < 0.0);
+    polygon.len() >= 3 && (left || right)
+}
+
+fn center(polygon: &[Vec2]) -> Vec2 {
+    polygon.iter().sum::<Vec2>() / polygon.len() as f32
+}
+
+fn project(polygon: &[Vec2], axis: Vec2) -> (f32, f32) {
+    polygon
+        .iter()
+        .map(|point| point.dot(axis))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), p| {
+            (min.min(p), max.max(p))
+        })
+}
+
+// The shortest move that pushes `a` out of `b`, or None when they don't overlap.
+// Only the edge normals of both polygons can separate them, so those are the only axes to check.
+pub fn penetration(a: &[Vec2], b: &[Vec2]) -> Option<Vec2> {
+    let mut shortest: Option<Vec2> = None;
+    for polygon in [a, b] {
+        for i in 0..polygon.len() {
+            let edge = polygon[(i + 1) % polygon.len()] - polygon[i];
+            let Some(axis) = edge.perp().try_normalize() else {
+                continue;
+            };
+            let (min_a, max_a) = project(a, axis);
+            let (min_b, max_b) = project(b, axis);
+            let overlap = max_a.min(max_b) - min_a.max(min_b);
+            if overlap <= 0.0 {
+                return None;
+            }
+            if shortest.is_none_or(|push| overlap < push.length()) {
+                // Point the push from b towards a
+                let axis = if (center(a) - center(b)).dot(axis) < 0.0 {
+                    -axis
+                } else {
+                    axis
+                };
+                shortest = Some(axis * overlap);
+            }
+        }
+    }
+    shortest
+}
+
+// The side of `b` that `a` hit, from the push out of `penetration`
+pub fn side(push: Vec2) -> Collision {
+    if push.y.abs() >= push.x.abs() {
+        if push.y > 0.0 {
+            Collision::Top
+        } else {
+            Collision::Bottom
+        }
+    } else if push.x < 0.0 {
+        Collision::Left
+    } else {
+        Collision::Right
+    }
+}
+
+// The center of where the bounding boxes of both polygons overlap
+pub fn contact_point(a: &[Vec2], b: &[Vec2]) -> Vec2 {
+    let bounds = |polygon: &[Vec2]| {
+        polygon.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), &p| (min.min(p), max.max(p)),
+        )
+    };
+    let ((min_a, max_a), (min_b, max_b)) = (bounds(a), bounds(b));
+    (min_a.max(min_b) + max_a.min(max_b)) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec2, b: Vec2) {
+        assert!((a - b).length() < 1e-3, "{a} != {b}");
+    }
+
+    #[test]
+    fn rotated_box_reaches_further_than_its_bounds() {
+        let target = rectangle(Vec2::new(45.0, 0.0), Vec2::splat(40.0));
+        let square = rectangle(Vec2::ZERO, Vec2::splat(40.0));
+        assert_eq!(penetration(&square, &target), None);
+        // Turned by 45 degrees, a corner points into the target
+        let turned = transformed(
+            &square,
+            &Transform::from_rotation(Quat::from_rotation_z(0.25 * std::f32::consts::PI)),
+        );
+        let push = penetration(&turned, &target).unwrap();
+        // The corner sticks out 20 * sqrt(2) from the center, 25 is where the target starts
+        assert_near(push, Vec2::new(25.0 - 800f32.sqrt(), 0.0));
+        assert_eq!(side(push), Collision::Left);
+        // Further away, the corner misses too
+        let farther = rectangle(Vec2::new(50.0, 0.0), Vec2::splat(40.0));
+        assert_eq!(penetration(&turned, &farther), None);
+    }
+
+    #[test]
+    fn chamfered_corner_lets_a_near_miss_pass() {
+        let roof = [
+            Vec2::new(-60.0, -72.0),
+            Vec2::new(60.0, -72.0),
+            Vec2::new(60.0, 64.0),
+            Vec2::new(52.0, 72.0),
+            Vec2::new(-52.0, 72.0),
+            Vec2::new(-60.0, 64.0),
+        ];
+        // Inside the bounds of the roof, but just outside the cut corner
+        let bundle = rectangle(Vec2::new(62.0, 73.0), Vec2::splat(10.0));
+        assert_eq!(penetration(&bundle, &roof), None);
+        let square = rectangle(Vec2::ZERO, Vec2::new(120.0, 144.0));
+        assert!(penetration(&bundle, &square).is_some());
+    }
+
+    #[test]
+    fn push_points_out_of_the_face_that_was_hit() {
+        let house = rectangle(Vec2::ZERO, Vec2::splat(100.0));
+        for (center, push, hit_side) in [
+            (Vec2::new(0.0, 53.0), Vec2::new(0.0, 2.0), Collision::Top),
+            (
+                Vec2::new(0.0, -53.0),
+                Vec2::new(0.0, -2.0),
+                Collision::Bottom,
+            ),
+            (Vec2::new(-53.0, 0.0), Vec2::new(-2.0, 0.0), Collision::Left),
+            (Vec2::new(53.0, 0.0), Vec2::new(2.0, 0.0), Collision::Right),
+        ] {
+            let stork = rectangle(center, Vec2::splat(10.0));
+            let found = penetration(&stork, &house).unwrap();
+            assert_near(found, push);
+            assert_eq!(side(found), hit_side, "{center}");
+            // Halfway into the overlap of both, on the face
+            assert_near(
+                contact_point(&stork, &house),
+                center - center.normalize() * 4.0,
+            );
+        }
+    }
+
+    #[test]
+    fn concave_and_flat_polygons_are_not_convex() {
+        assert!(is_convex(&rectangle(Vec2::ZERO, Vec2::ONE)));
+        let dented = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(10.0, 0.0),
+            Vec2::new(10.0, 10.0),
+            Vec2::new(5.0, 5.0),
+            Vec2::new(0.0, 10.0),
+        ];
+        assert!(!is_convex(&dented));
+        let flat = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(5.0, 0.0),
+            Vec2::new(10.0, 0.0),
+        ];
+        assert!(!is_convex(&flat));
+    }
+}
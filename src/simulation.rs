@@ -0,0 +1,329 @@
+use bevy::prelude::*;
+
+use crate::{
+    delivery::{bundle_landing, drop_bundle, move_bundles, Delivered, Deliveries},
+    flight::FlightModel,
+    game_state::{end_run, game_over_on_collision, no_stork, run_going, GameState, RunOutcome},
+    replay::{replay_tick, start_replay},
+    score::{update_score, Score},
+    settings::{apply_settings, GameSettings},
+    stork::{blow_wind, move_stork, perch_on_roofs, spawn_stork, StorkInput},
+    tick::{store_previous_transforms, TICK_RATE},
+    world::{
+        collidables::{bounce_off_collidables, stork_hit_collidable, StorkCollision},
+        layouts::TileLayouts,
+        tiles::spawn_tiles,
+        wind::Wind,
+        World,
+    },
+};
+
+// The game logic without anything that needs a window, shared by the game and the headless runs.
+// Add it after the Bevy plugins, so its tick rate replaces the default one.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            // States
+            .add_state::<GameState>()
+            // Resources
+            .init_resource::<GameSettings>()
+            .init_resource::<World>()
+            .init_resource::<TileLayouts>()
+            .init_resource::<FlightModel>()
+            .init_resource::<StorkInput>()
+            .insert_resource(FixedTime::new_from_secs(1.0 / TICK_RATE))
+            .init_resource::<Deliveries>()
+            .init_resource::<Score>()
+            .init_resource::<RunOutcome>()
+            .init_resource::<Wind>()
+            // Events
+            .add_event::<Delivered>()
+            .add_event::<StorkCollision>()
+            // State transitions
+            .add_system(end_run.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(end_run.in_schedule(OnExit(GameState::GameOver)))
+            .add_systems(
+                (apply_settings, spawn_stork, start_replay)
+                    .distributive_run_if(no_stork)
+                    .in_schedule(OnEnter(GameState::Playing)),
+            )
+            // Simulation, in a fixed order so every run with the same input is the same
+            .add_systems(
+                (
+                    store_previous_transforms,
+                    replay_tick,
+                    blow_wind,
+                    move_stork,
+                    drop_bundle,
+                    move_bundles,
+                    stork_hit_collidable,
+                    perch_on_roofs,
+                    game_over_on_collision,
+                    bounce_off_collidables,
+                    bundle_landing,
+                    update_score,
+                    spawn_tiles,
+                )
+                    .chain()
+                    .distributive_run_if(in_state(GameState::Playing))
+                    .distributive_run_if(run_going)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        args::Args,
+        flight::{heading, FlightControls},
+        game_state::Crash,
+        replay::{live_input, InputSource, Replay},
+        stork::Stork,
+        world::{
+            catalogue::CollidableCatalogue,
+            collidables::{Collidable, DeliveryTarget},
+            difficulty::Difficulty,
+        },
+        PIXELS_PER_METER,
+    };
+
+    // What a test run did
+    pub struct Flight {
+        pub trajectory: Vec<Vec2>, // where the stork was after every tick
+        pub distance: f32,
+        pub deliveries: u32,
+        pub crash: Option<Crash>,
+        pub input_source: InputSource, // with the recording of a live run
+    }
+
+    // The inputs of a live run, one per tick. The run ends when they run out.
+    #[derive(Resource)]
+    struct Script {
+        inputs: Vec<StorkInput>,
+        tick: usize,
+    }
+
+    #[derive(Resource, Default)]
+    struct Trajectory(Vec<Vec2>);
+
+    fn play_script(
+        mut script: ResMut<Script>,
+        mut input: ResMut<StorkInput>,
+        mut next_state: ResMut<NextState<GameState>>,
+        mut run_outcome: ResMut<RunOutcome>,
+    ) {
+        match script.inputs.get(script.tick) {
+            Some(&next) => *input = next,
+            None => run_outcome.end(None, &mut next_state),
+        }
+        script.tick += 1;
+    }
+
+    fn record_trajectory(mut trajectory: ResMut<Trajectory>, stork_query: Query<&Stork>) {
+        trajectory
+            .0
+            .push(stork_query.get_single().unwrap().position);
+    }
+
+    // Flies one run on `seed` without a window, `ticks_per_update` ticks every frame. A live run follows `script`.
+    pub fn fly(
+        seed: u64,
+        settings: GameSettings,
+        input_source: InputSource,
+        script: Vec<StorkInput>,
+        ticks_per_update: u32,
+    ) -> Flight {
+        let difficulty = Difficulty::read().unwrap();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(SimulationPlugin)
+            .insert_resource(Args {
+                seed: Some(seed),
+                ..default()
+            })
+            .insert_resource(settings)
+            .insert_resource(input_source)
+            .insert_resource(TileLayouts::read().unwrap())
+            .insert_resource(CollidableCatalogue::read().unwrap())
+            .insert_resource(World::new(seed, difficulty.clone()))
+            .insert_resource(difficulty)
+            .insert_resource(Script {
+                inputs: script,
+                tick: 0,
+            })
+            .init_resource::<Trajectory>()
+            .add_system(
+                play_script
+                    .before(replay_tick)
+                    .run_if(live_input)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(run_going)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                record_trajectory
+                    .after(move_stork)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(run_going)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+        app.world
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+
+        let period = app.world.resource::<FixedTime>().period;
+        let mut now = Instant::now();
+        for _ in 0..100_000 {
+            app.insert_resource(TimeUpdateStrategy::ManualInstant(now));
+            app.update();
+            if app.world.resource::<State<GameState>>().0 == GameState::GameOver {
+                break;
+            }
+            now += period * ticks_per_update;
+        }
+        assert_eq!(
+            app.world.resource::<State<GameState>>().0,
+            GameState::GameOver,
+            "the run didn't end"
+        );
+
+        Flight {
+            trajectory: app.world.remove_resource::<Trajectory>().unwrap().0,
+            distance: app.world.resource::<Score>().distance,
+            deliveries: app.world.resource::<Deliveries>().count,
+            crash: app.world.resource::<RunOutcome>().crash.clone(),
+            input_source: app.world.remove_resource::<InputSource>().unwrap(),
+        }
+    }
+
+    // Cruises along, flapping and climbing now and then and dropping bundles, then dives into the ground
+    pub fn script(cruise_ticks: usize) -> Vec<StorkInput> {
+        let cruise = (0..cruise_ticks).map(|tick| StorkInput {
+            controls: FlightControls {
+                thrust: tick % 120 < 50,
+                pitch_up: tick % 240 < 12,
+                pitch_down: tick % 240 >= 120 && tick % 240 < 126,
+            },
+            drop_bundle: tick % 150 == 0,
+        });
+        let dive = (0..600).map(|_| StorkInput {
+            controls: FlightControls {
+                pitch_down: true,
+                ..default()
+            },
+            ..default()
+        });
+        cruise.chain(dive).collect()
+    }
+
+    pub fn live() -> InputSource {
+        InputSource::Live {
+            recording: Replay::new(0, GameSettings::default()),
+        }
+    }
+
+    #[test]
+    fn runs_are_the_same_whatever_the_ticks_per_frame() {
+        let one = fly(3, GameSettings::default(), live(), script(900), 1);
+        assert!(one.crash.is_some(), "the dive ends in a crash");
+        for ticks_per_update in [2, 5, 7] {
+            let many = fly(
+                3,
+                GameSettings::default(),
+                live(),
+                script(900),
+                ticks_per_update,
+            );
+            assert_eq!(many.trajectory, one.trajectory, "{ticks_per_update}");
+            assert_eq!(many.distance, one.distance, "{ticks_per_update}");
+            assert_eq!(many.deliveries, one.deliveries, "{ticks_per_update}");
+            assert_eq!(many.crash, one.crash, "{ticks_per_update}");
+        }
+    }
+
+    #[test]
+    fn invulnerability_survives_a_crash_into_the_ground() {
+        let mut settings = GameSettings::default();
+        settings.assists.invulnerability = false;
+        let crashed = fly(3, settings, live(), script(900), 1);
+        assert_eq!(crashed.crash, Some(Crash::Ground));
+        settings.assists.invulnerability = true;
+        let survived = fly(3, settings, live(), script(900), 1);
+        assert!(survived.trajectory.len() > crashed.trajectory.len());
+        assert_eq!(
+            survived.trajectory[..crashed.trajectory.len() - 1],
+            crashed.trajectory[..crashed.trajectory.len() - 1]
+        );
+    }
+
+    // Holds the stork still at `position`, in meters, and lets it drop its bundle there if it has one. Runs until
+    // the bundle has landed, and the tiles around the stork are spawned.
+    fn hover_and_drop(app: &mut App, position: Vec2) {
+        let mut stork_query = app.world.query::<&mut Stork>();
+        let mut stork = stork_query.single_mut(&mut app.world);
+        stork.position = position;
+        // A headwind that cancels the speed of the stork, so the bundle falls straight down
+        stork.wind = -heading(stork.direction()) * stork.speed();
+        app.world.resource_mut::<StorkInput>().drop_bundle = true;
+        for _ in 0..10 * TICK_RATE as usize {
+            app.update();
+            if stork_query.single(&app.world).has_bundle {
+                return;
+            }
+        }
+        panic!("the bundle never landed");
+    }
+
+    #[test]
+    fn a_house_gets_its_baby_only_once() {
+        let mut app = App::new();
+        app.insert_resource(TileLayouts::read().unwrap())
+            .insert_resource(CollidableCatalogue::read().unwrap())
+            .insert_resource(World::new(1, Difficulty::read().unwrap()))
+            .insert_resource(FixedTime::new_from_secs(1.0 / TICK_RATE))
+            .init_resource::<GameSettings>()
+            .init_resource::<FlightModel>()
+            .init_resource::<StorkInput>()
+            .init_resource::<Deliveries>()
+            .add_event::<Delivered>()
+            .add_startup_system(spawn_stork)
+            .add_systems((drop_bundle, move_bundles, bundle_landing, spawn_tiles).chain());
+        app.update();
+        hover_and_drop(&mut app, Vec2::new(0.0, 20.0));
+
+        let mut target_query = app
+            .world
+            .query_filtered::<(&Transform, &Collidable), With<DeliveryTarget>>();
+        let (transform, collidable) = target_query
+            .iter(&app.world)
+            .min_by(|(a, _), (b, _)| a.translation.x.abs().total_cmp(&b.translation.x.abs()))
+            .expect("there is a house waiting for a baby near the start");
+        let roof_top = collidable
+            .world_shapes(transform)
+            .flatten()
+            .map(|point| point.y)
+            .fold(f32::MIN, f32::max);
+        let above_roof = Vec2::new(transform.translation.x, roof_top + 50.0) / PIXELS_PER_METER;
+
+        hover_and_drop(&mut app, above_roof);
+        assert_eq!(app.world.resource::<Deliveries>().count, 1);
+
+        // Far enough away that the house is despawned, and back
+        hover_and_drop(&mut app, above_roof + Vec2::new(200.0, 0.0));
+        let mut house_query = app.world.query_filtered::<&Transform, With<Collidable>>();
+        assert!(house_query.iter(&app.world).all(|transform| {
+            (transform.translation.x / PIXELS_PER_METER - above_roof.x).abs() > 50.0
+        }));
+        hover_and_drop(&mut app, above_roof);
+        hover_and_drop(&mut app, above_roof);
+        assert_eq!(app.world.resource::<Deliveries>().count, 1);
+    }
+}
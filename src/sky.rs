@@ -0,0 +1,166 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use rand::prelude::*;
+
+use crate::{
+    game_state::GameState,
+    stork::Stork,
+    world::{wind::Wind, World},
+    PIXELS_PER_METER,
+};
+
+// Shows the weather and the light around the stork: a veil of fog or darkness over what lies out of sight,
+// and rain. Like the leaves, it is only for show.
+pub struct SkyPlugin;
+
+impl Plugin for SkyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_veil.in_schedule(OnExit(GameState::MainMenu)))
+            .add_system(despawn_sky.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(despawn_raindrops.in_schedule(OnExit(GameState::GameOver)))
+            .add_systems(
+                (update_veil, spawn_raindrops, fall_raindrops).in_set(OnUpdate(GameState::Playing)),
+            );
+    }
+}
+
+// One of the vertical strips that together make up the veil, from the left of the view to the right
+#[derive(Component)]
+struct VeilStrip(usize);
+
+#[derive(Component)]
+struct Raindrop {
+    velocity: Vec2, // in meters per second
+}
+
+// Everything the sky has spawned
+type SkyEntity = Or<(With<VeilStrip>, With<Raindrop>)>;
+
+const VEIL_STRIPS: usize = 48;
+const VEIL_Z: f32 = 8.0; // over the game and the leaves, under the debug view
+
+// How much of the veil shows right at the stork, and far out of sight
+const NEAR_VEIL: f32 = 0.1;
+const FAR_VEIL: f32 = 0.95;
+
+const RAIN_SPEED: f32 = 8.0; // in meters per second
+const RAINDROP_SIZE: Vec2 = Vec2 { x: 2.0, y: 14.0 }; // in pixels
+const RAINDROP_COLOR: Color = Color::rgba(0.7, 0.75, 0.9, 0.6);
+
+fn spawn_veil(mut commands: Commands) {
+    for i in 0..VEIL_STRIPS {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::NONE,
+                    ..default()
+                },
+                ..default()
+            },
+            VeilStrip(i),
+        ));
+    }
+}
+
+fn despawn_sky(mut commands: Commands, sky_query: Query<Entity, SkyEntity>) {
+    for entity in sky_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn despawn_raindrops(mut commands: Commands, raindrop_query: Query<Entity, With<Raindrop>>) {
+    for entity in raindrop_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+// The further a strip is from the stork, compared to how far it can see, the more it hides
+fn update_veil(
+    mut world: ResMut<World>,
+    stork_query: Query<&Stork>,
+    camera_query: Query<&Transform, (With<Camera2d>, Without<VeilStrip>)>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut strip_query: Query<(&VeilStrip, &mut Transform, &mut Sprite)>,
+) {
+    let (Ok(stork), Ok(camera), Ok(window)) = (
+        stork_query.get_single(),
+        camera_query.get_single(),
+        window_query.get_single(),
+    ) else {
+        return;
+    };
+    let conditions = world.conditions(stork.position.x);
+    let visibility = conditions.visibility();
+    let view = Vec2::new(window.width(), window.height()) * camera.scale.truncate();
+    let strip_width = view.x / VEIL_STRIPS as f32;
+    let left = camera.translation.x - view.x / 2.0;
+    for (strip, mut transform, mut sprite) in strip_query.iter_mut() {
+        let x = left + (strip.0 as f32 + 0.5) * strip_width;
+        let distance = (x / PIXELS_PER_METER - stork.position.x).abs();
+        let hidden = (distance / visibility).min(1.0);
+        let veil = NEAR_VEIL + (FAR_VEIL - NEAR_VEIL) * hidden * hidden;
+        sprite.color = conditions.veil().with_a(conditions.veil_strength() * veil);
+        // Overlap the neighbours by a pixel, so there are no gaps
+        sprite.custom_size = Some(Vec2::new(strip_width + 1.0, view.y));
+        transform.translation = Vec3::new(x, camera.translation.y, VEIL_Z);
+    }
+}
+
+// Rain falls in from the top of the view, as much as the weather over the stork brings
+fn spawn_raindrops(
+    mut commands: Commands,
+    time: Res<Time>,
+    wind: Res<Wind>,
+    mut world: ResMut<World>,
+    stork_query: Query<&Stork>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+) {
+    let (Ok(stork), Ok(camera), Ok(window)) = (
+        stork_query.get_single(),
+        camera_query.get_single(),
+        window_query.get_single(),
+    ) else {
+        return;
+    };
+    let mut rng = thread_rng();
+    let expected = world.conditions(stork.position.x).rain() * time.delta_seconds();
+    let count = expected as usize + usize::from(rng.gen::<f32>() < expected.fract());
+    let half_view = Vec2::new(window.width(), window.height()) * camera.scale.truncate() / 2.0;
+    for _ in 0..count {
+        let position = Vec2::new(
+            camera.translation.x + rng.gen_range(-half_view.x..half_view.x),
+            camera.translation.y + half_view.y,
+        );
+        let air = wind.at(&mut world, position / PIXELS_PER_METER, []);
+        let velocity = air - Vec2::new(0.0, RAIN_SPEED);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: RAINDROP_COLOR,
+                    custom_size: Some(RAINDROP_SIZE),
+                    ..default()
+                },
+                // Streak along the way the drop falls
+                transform: Transform::from_translation(position.extend(6.0))
+                    .with_rotation(Quat::from_rotation_z(velocity.x.atan2(-velocity.y))),
+                ..default()
+            },
+            Raindrop { velocity },
+        ));
+    }
+}
+
+fn fall_raindrops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut raindrop_query: Query<(Entity, &Raindrop, &mut Transform)>,
+) {
+    for (entity, raindrop, mut transform) in raindrop_query.iter_mut() {
+        transform.translation +=
+            (raindrop.velocity * time.delta_seconds() * PIXELS_PER_METER).extend(0.0);
+        // Gone once it reaches the ground
+        if transform.translation.y < 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
@@ -1,93 +1,298 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
 
-use super::PIXELS_PER_METER;
+use crate::{
+    flight::{heading, FlightControls, FlightModel, FlightState, GroundContact, Stamina},
+    game_state::{Crash, GameState, RunOutcome},
+    load_texture,
+    settings::GameSettings,
+    tick::TickTransform,
+    world::{
+        collidables::{Collidable, StorkCollision},
+        wind::Wind,
+        World,
+    },
+    PIXELS_PER_METER,
+};
 
 #[derive(Component)]
 pub struct Stork {
     pub position: Vec2,
     speed: f32,
     direction: f32, // in degrees, 90 is to the right, 0 is down, -90 is to the left
+    pub has_bundle: bool,
+    pub on_ground: bool,
+    pub perch: Option<Perch>,
+    pub stamina: Stamina,
+    pub wind: Vec2,   // how the air around the stork moves, in meters per second
+    pub hitbox: Vec2, // the size of the box that collides, in pixels
+    pub survives_hits: bool, // with the invulnerability assist
+    pub invulnerable: f32, // in seconds, how much longer nothing can hit the stork
+}
+
+// A roof the stork is standing on
+#[derive(Clone, Copy, Debug)]
+pub struct Perch {
+    pub collidable: Entity,
+    height: f32, // of the center of the stork, in meters
+}
+
+impl Stork {
+    // in meters per second
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    // in degrees, 90 is to the right, 0 is down, -90 is to the left
+    pub fn direction(&self) -> f32 {
+        self.direction
+    }
+
+    pub fn flight_state(&self) -> FlightState {
+        FlightState {
+            position: self.position,
+            speed: self.speed,
+            direction: self.direction,
+        }
+    }
+
+    // The velocity of the stork over the ground in meters per second, through the air and with it
+    pub fn velocity(&self) -> Vec2 {
+        heading(self.direction) * self.speed + self.wind
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    // Whether a crash into the ground or something lethal leaves the stork flying. With the invulnerability
+    // assist, a crash costs stamina instead of the run, as long as the stork has enough, and nothing can hit it
+    // for a moment after.
+    pub fn survive_hit(&mut self) -> bool {
+        if self.is_invulnerable() {
+            return true;
+        }
+        if !self.survives_hits || self.stamina.level < HIT_STAMINA {
+            return false;
+        }
+        self.stamina.level -= HIT_STAMINA;
+        self.invulnerable = INVULNERABILITY_TIME;
+        true
+    }
+
+    pub fn perched_on(&self, collidable: Entity) -> bool {
+        self.perch
+            .is_some_and(|perch| perch.collidable == collidable)
+    }
+
+    // Mirrors the flight off the side of something the stork flew into, losing some speed.
+    // It only bounces when it is still flying into that side, so it doesn't bounce back in.
+    pub fn bounce(&mut self, side: &Collision) {
+        let velocity = self.velocity();
+        let (into_side, direction) = match side {
+            Collision::Top => (
+                velocity.y < 0.0,
+                self.direction.signum() * 180.0 - self.direction,
+            ),
+            Collision::Bottom => (
+                velocity.y > 0.0,
+                self.direction.signum() * 180.0 - self.direction,
+            ),
+            Collision::Left => (velocity.x > 0.0, -self.direction),
+            Collision::Right => (velocity.x < 0.0, -self.direction),
+            Collision::Inside => (false, self.direction),
+        };
+        if into_side {
+            self.direction = direction;
+            self.speed *= BOUNCE_DAMPING;
+        }
+    }
+}
+
+// The height of the center of the stork when it stands on the ground, in meters
+const GROUND_HEIGHT: f32 = 0.16;
+// How far a perched stork sinks into the roof, so it keeps touching it, in meters
+const PERCH_DEPTH: f32 = 0.01;
+
+// The part of its speed that the stork keeps when it bounces
+const BOUNCE_DAMPING: f32 = 0.8;
+
+// The size of the stork sprite, in pixels
+const SIZE_STORK: Vec2 = Vec2 { x: 64.0, y: 32.0 };
+
+// The stamina that a hit costs with the invulnerability assist
+const HIT_STAMINA: f32 = 0.5;
+// How long the stork can't be hit after a hit, in seconds
+const INVULNERABILITY_TIME: f32 = 2.0;
+
+// How often an invulnerable stork blinks, per second
+const BLINK_RATE: f32 = 8.0;
+
+// The controls of the stork, read from the keyboard every frame and used by the next tick
+#[derive(Resource, Default, Clone, Copy)]
+pub struct StorkInput {
+    pub controls: FlightControls,
+    pub drop_bundle: bool, // stays set until a tick has dropped the bundle
 }
 
 // spawn the sork at the origin, and spawn the sprite.
 pub fn spawn_stork(
     mut commands: Commands,
-    window_querry: Query<&Window, With<PrimaryWindow>>,
-    asset_server: Res<AssetServer>,
+    asset_server: Option<Res<AssetServer>>,
+    settings: Res<GameSettings>,
 ) {
-    let window = window_querry.get_single().unwrap();
+    let stork = Stork {
+        position: Vec2::new(0.0, 3.0),
+        speed: 2.0,
+        direction: 100.0,
+        has_bundle: true,
+        on_ground: false,
+        perch: None,
+        stamina: Stamina::default(),
+        wind: Vec2::ZERO,
+        hitbox: SIZE_STORK * settings.preset.hitbox_scale(),
+        survives_hits: settings.assists.invulnerability,
+        invulnerable: 0.0,
+    };
+    let transform = stork_transform(stork.position, stork.direction);
     commands.spawn((
         SpriteBundle {
-            transform: Transform::from_xyz(window.width() / 2.0, window.height() / 2.0, 0.0),
-            texture: asset_server.load("sprites\\stork.png"),
+            transform,
+            texture: load_texture(asset_server.as_deref(), "sprites\\stork.png"),
             ..default()
         },
-        Stork {
-            position: Vec2::new(0.0, 3.0),
-            speed: 2.0,
-            direction: 100.0,
-        },
+        TickTransform::new(transform),
+        stork,
     ));
 }
 
-const AIR_RESISTANCE: f32 = 0.1;
-const GRAVITY: f32 = 9.81 * 0.2;
-const STALL_SPEED: f32 = 1.0;
-
-const MAX_ACCELLEATION: f32 = 10.0;
-const TOP_ACCELERATION_SPEED: f32 = 4.0;
-const TURN_SPEED: f32 = 100.0;
+pub fn read_stork_input(keyboard_input: Res<Input<KeyCode>>, mut input: ResMut<StorkInput>) {
+    input.controls = FlightControls {
+        thrust: keyboard_input.pressed(KeyCode::LShift),
+        pitch_up: keyboard_input.pressed(KeyCode::Up),
+        pitch_down: keyboard_input.pressed(KeyCode::Down),
+    };
+    input.drop_bundle |= keyboard_input.just_pressed(KeyCode::Space);
+}
 
 pub fn move_stork(
-    time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mut query: Query<(&mut Stork, &mut Transform)>,
+    fixed_time: Res<FixedTime>,
+    input: Res<StorkInput>,
+    flight_model: Res<FlightModel>,
+    mut query: Query<(&mut Stork, &mut TickTransform)>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut run_outcome: ResMut<RunOutcome>,
+    mut world: ResMut<World>,
 ) {
-    let (mut stork, mut transf) = query.get_single_mut().unwrap();
-    let mut speed = stork.speed;
-
-    // apply power on keyboard input (shift)
-    // At speed 0, the acceleration is maximum. At speed TOP_ACCELERATION_SPEED, the acceleration is 0
-    // At speeds beyond TOP_ACCELERATION_SPEED, the acceleration is negative
-    if keyboard_input.pressed(KeyCode::LShift) {
-        let acceleration = MAX_ACCELLEATION * (1.0 - speed / TOP_ACCELERATION_SPEED);
-        speed += acceleration * time.delta_seconds();
-    }
-
-    // on using the up and down arrows, change the direction
-    if keyboard_input.pressed(KeyCode::Up) {
-        stork.direction += TURN_SPEED * stork.direction.signum() * time.delta_seconds();
-        if stork.direction.abs() > 175.0 {
-            stork.direction = -stork.direction.signum() * 180.0;
-        }
+    let (mut stork, mut tick_transform) = query.get_single_mut().unwrap();
+    let dt = fixed_time.period.as_secs_f32();
+    stork.invulnerable = (stork.invulnerable - dt).max(0.0);
+    // Rain makes the air harder to fly through
+    let flight_model = world
+        .conditions(stork.position.x)
+        .flight_model(&flight_model);
+    let mut state = stork.flight_state();
+    let standing = stork.on_ground || stork.perch.is_some();
+    let (stamina, controls) = flight_model.use_stamina(stork.stamina, input.controls, standing, dt);
+    stork.stamina = stamina;
+    if standing && controls.thrust {
+        state = flight_model.take_off(state);
+    }
+    let mut state = flight_model.step(state, controls, dt);
+    // The flight model moves the stork through the air, and the air carries it along
+    state.position += stork.wind * dt;
+    // A roof carries the stork like the ground does
+    let (ground_height, on_ground) = match stork.perch {
+        Some(perch) => (perch.height, true),
+        None => (GROUND_HEIGHT, stork.on_ground),
+    };
+    let (mut state, mut contact) = flight_model.touch_ground(state, on_ground, ground_height, dt);
+    // A crash that the stork survives still brings it down
+    if contact == GroundContact::Crashed && stork.survive_hit() {
+        (state, contact) = flight_model.touch_ground(state, true, ground_height, dt);
+    }
+    if contact == GroundContact::Crashed {
+        run_outcome.end(Some(Crash::Ground), &mut next_state);
+    }
+    if contact == GroundContact::Airborne {
+        stork.perch = None;
     }
-    if keyboard_input.pressed(KeyCode::Down) {
-        stork.direction -= TURN_SPEED * stork.direction.signum() * time.delta_seconds();
-        if stork.direction.abs() < 5.0 {
-            stork.direction = -stork.direction.signum() * 0.0;
+    stork.on_ground = contact == GroundContact::Landed && stork.perch.is_none();
+    stork.position = state.position;
+    stork.speed = state.speed;
+    stork.direction = state.direction;
+    tick_transform.current = stork_transform(stork.position, stork.direction);
+}
+
+// Runs every tick before the stork moves. A stork standing on the ground or a roof is out of the wind.
+pub fn blow_wind(
+    fixed_time: Res<FixedTime>,
+    flight_model: Res<FlightModel>,
+    mut wind: ResMut<Wind>,
+    mut world: ResMut<World>,
+    collidable_query: Query<(&Collidable, &Transform)>,
+    mut stork_query: Query<&mut Stork>,
+) {
+    wind.advance(fixed_time.period.as_secs_f32());
+    let mut stork = stork_query.get_single_mut().unwrap();
+    let standing = stork.on_ground || stork.perch.is_some();
+    let new_wind = if standing {
+        Vec2::ZERO
+    } else {
+        wind.at(&mut world, stork.position, collidable_query.iter())
+    };
+    let state = flight_model.feel_wind_change(stork.flight_state(), new_wind - stork.wind);
+    stork.speed = state.speed;
+    stork.wind = new_wind;
+}
+
+// Runs every tick after the collisions are known. A slow, level touchdown on top of a house perches the stork
+// there instead of crashing it. Walking off the edge of the roof ends the perch.
+pub fn perch_on_roofs(
+    mut collision_event_reader: EventReader<StorkCollision>,
+    flight_model: Res<FlightModel>,
+    mut stork_query: Query<(&mut Stork, &mut TickTransform)>,
+) {
+    let (mut stork, mut tick_transform) = stork_query.get_single_mut().unwrap();
+    let mut still_perched = false;
+    for collision in collision_event_reader.iter() {
+        if stork.perched_on(collision.collidable) {
+            still_perched = true;
+        } else if collision.side == Collision::Top
+            && !stork.on_ground
+            && flight_model.can_land(stork.flight_state())
+        {
+            // Stand on the roof, sunk in just enough to keep touching it
+            stork.position += collision.push / PIXELS_PER_METER;
+            stork.position.y -= PERCH_DEPTH;
+            stork.perch = Some(Perch {
+                collidable: collision.collidable,
+                height: stork.position.y,
+            });
+            if stork.direction.abs() < 90.0 {
+                stork.direction = 90.0 * stork.direction.signum();
+            }
+            tick_transform.current = stork_transform(stork.position, stork.direction);
+            still_perched = true;
         }
     }
+    if !still_perched {
+        stork.perch = None;
+    }
+}
+
+// An invulnerable stork blinks until it can be hit again
+pub fn blink_invulnerable_stork(time: Res<Time>, mut stork_query: Query<(&Stork, &mut Sprite)>) {
+    for (stork, mut sprite) in stork_query.iter_mut() {
+        let hidden = stork.is_invulnerable() && (time.elapsed_seconds() * BLINK_RATE).fract() < 0.5;
+        sprite.color.set_a(if hidden { 0.3 } else { 1.0 });
+    }
+}
 
-    // apply gravity. Change the speed based on conservations of energy
-    let delta_h = (stork.direction.abs() - 90.0).to_radians().sin() * speed * time.delta_seconds();
-    speed = (speed.powi(2) - 2.0 * (delta_h * GRAVITY)).sqrt();
-    assert!(!speed.is_nan(), "Error: Speed is NaN!");
-    // apply air resistance
-    speed -= speed * AIR_RESISTANCE * time.delta_seconds();
-    // turn downward if speed is below stall speed
-    if speed < STALL_SPEED {
-        let dir = stork.direction.signum();
-        let mut elevation = stork.direction.abs();
-        // use a curve to make the turn more gradual
-        // At the stall speed, the turn speed is 0, at speed 0, the turn speed is infinite
-        let turnspeed = 20.0 / (speed / STALL_SPEED).powi(2);
-        elevation -= turnspeed * time.delta_seconds();
-        stork.direction = elevation * dir as f32;
-    }
-    let dir = stork.direction - 90.0; // coorrection so 0 is to the right, so the math is as in the unit circle
-    stork.position +=
-        Vec2::new(dir.to_radians().cos(), dir.to_radians().sin()) * speed * time.delta_seconds();
-    transf.translation = Vec3::new(stork.position.x, stork.position.y, 0.0) * PIXELS_PER_METER;
-    transf.rotation = Quat::from_rotation_z(dir.to_radians());
-    stork.speed = speed;
+// Where a stork sprite is drawn, for a position in meters and a direction in degrees
+pub fn stork_transform(position: Vec2, direction: f32) -> Transform {
+    Transform {
+        translation: Vec3::new(position.x, position.y, 0.0) * PIXELS_PER_METER,
+        rotation: Quat::from_rotation_arc_2d(Vec2::X, heading(direction)),
+        ..default()
+    }
 }
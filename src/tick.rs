@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+
+// The simulation runs at a fixed rate, independent of the frame rate, so every run with the same
+// seed and input is the same. Rendering blends between the last two ticks.
+pub const TICK_RATE: f32 = 120.0;
+
+// The transform of an entity at the last two ticks. Simulation systems write `current`,
+// and the rendered `Transform` is interpolated between `previous` and `current`.
+#[derive(Component)]
+pub struct TickTransform {
+    pub previous: Transform,
+    pub current: Transform,
+}
+
+impl TickTransform {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            previous: transform,
+            current: transform,
+        }
+    }
+}
+
+// Runs at the start of every tick, before anything moves
+pub fn store_previous_transforms(mut query: Query<&mut TickTransform>) {
+    for mut tick_transform in query.iter_mut() {
+        tick_transform.previous = tick_transform.current;
+    }
+}
+
+// Runs every frame. The time left in the accumulator says how far we are between the last tick and the next one.
+pub fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut query: Query<(&TickTransform, &mut Transform)>,
+) {
+    let alpha = (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).min(1.0);
+    for (tick_transform, mut transform) in query.iter_mut() {
+        let (previous, current) = (tick_transform.previous, tick_transform.current);
+        transform.translation = previous.translation.lerp(current.translation, alpha);
+        transform.rotation = previous.rotation.slerp(current.rotation, alpha);
+    }
+}
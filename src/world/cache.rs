@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+// What was generated per tile (or per stretch) around where the world was last looked at. It only keeps the
+// entries closest to the last one that was stored, so a long run doesn't grow it forever. A dropped entry is
+// generated again when it is needed again, the same as before, because generation only depends on the seed.
+pub struct TileCache<V> {
+    entries: HashMap<i64, V>,
+    capacity: usize,
+}
+
+impl<V: Copy> TileCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: HashMap::with_capacity(capacity + 1),
+            capacity,
+        }
+    }
+
+    pub fn get(&self, key: i64) -> Option<V> {
+        self.entries.get(&key).copied()
+    }
+
+    // Stores `value`, and drops the entry farthest away from it when the cache is full
+    pub fn insert(&mut self, key: i64, value: V) {
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            let farthest = *self
+                .entries
+                .keys()
+                .max_by_key(|&&other| (other.abs_diff(key), other))
+                .unwrap();
+            self.entries.remove(&farthest);
+        }
+        self.entries.insert(key, value);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_keeps_the_entries_nearest_to_the_last_one() {
+        let mut cache = TileCache::new(3);
+        for key in [0, 1, 2, 3] {
+            cache.insert(key, key);
+        }
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get(0), None);
+        cache.insert(-1, -1);
+        assert_eq!(cache.get(3), None, "the farthest entry from -1 goes first");
+        assert_eq!(cache.get(1), Some(1));
+    }
+}
@@ -0,0 +1,189 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use serde::Deserialize;
+
+use crate::shapes;
+
+use super::{
+    collidables::Collidable,
+    ron_asset::{reload, RonAsset, RonAssetPlugin, RonHandles},
+    tiles::Tile,
+};
+
+// Everything the stork can fly into, by name, from assets/catalogue.collidables.ron.
+// Until it is loaded there is no such resource, and no tiles are spawned.
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "0b7c3f5e-8a21-4d6f-b2e9-7f14a6c3d958"]
+#[serde(transparent)]
+pub struct CollidableCatalogue {
+    entries: HashMap<String, CollidableDef>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CollidableDef {
+    pub sprite: String, // relative to assets/
+    size: (f32, f32),   // of the hitbox, in pixels
+    #[serde(default)]
+    offset: (f32, f32), // of the hitbox from the center of the sprite, in pixels
+    #[serde(default)]
+    shapes: Vec<Vec<(f32, f32)>>, // convex polygons in pixels from the center of the sprite, the hitbox if none
+    #[serde(default = "default_z")]
+    pub z: f32,
+    #[serde(default = "default_lethal")]
+    lethal: bool, // flying into it ends the run
+    #[serde(default)]
+    bouncy: bool, // the stork bounces off it, if it isn't lethal
+    #[serde(default)]
+    updraft: f32, // how fast the wind rises in front of it, in meters per second at a steady wind of full strength
+    #[serde(default)]
+    pub delivery_target: bool, // it can wait for a baby
+    #[serde(default)]
+    pub animation: Option<AnimationDef>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AnimationDef {
+    frames: Vec<String>, // sprites, relative to assets/
+    fps: f32,
+}
+
+// Cycles the sprite of a collidable through its frames
+#[derive(Component)]
+pub struct Animation {
+    frames: Vec<Handle<Image>>,
+    fps: f32,
+}
+
+fn default_z() -> f32 {
+    -0.5
+}
+
+fn default_lethal() -> bool {
+    true
+}
+
+const CATALOGUE_PATH: &str = "catalogue.collidables.ron";
+
+// Loads the catalogue through the asset server, hot reloads it, and animates the collidables
+pub struct CataloguePlugin;
+
+impl Plugin for CataloguePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<CollidableCatalogue>::new([
+            CATALOGUE_PATH.to_string()
+        ]))
+        .add_system(update_catalogue)
+        .add_system(animate_collidables);
+    }
+}
+
+impl RonAsset for CollidableCatalogue {
+    const EXTENSIONS: &'static [&'static str] = &["collidables.ron"];
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let catalogue: Self = ron::de::from_bytes(bytes).map_err(|err| err.to_string())?;
+        for (name, def) in &catalogue.entries {
+            if def.size.0 <= 0.0 || def.size.1 <= 0.0 {
+                return Err(format!("{name} has no hitbox"));
+            }
+            if let Some(shape) = def.shapes().iter().find(|shape| !shapes::is_convex(shape)) {
+                return Err(format!("a shape of {name} is not convex: {shape:?}"));
+            }
+            if def.updraft < 0.0 {
+                return Err(format!("{name} has an updraft that blows down"));
+            }
+            if def
+                .animation
+                .as_ref()
+                .is_some_and(|animation| animation.frames.is_empty() || animation.fps <= 0.0)
+            {
+                return Err(format!("the animation of {name} never shows a frame"));
+            }
+        }
+        Ok(catalogue)
+    }
+}
+
+impl CollidableCatalogue {
+    // Reads the catalogue straight from the asset folder, for when there is no asset server
+    pub fn read() -> Result<Self, String> {
+        Self::read_from(CATALOGUE_PATH)
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&CollidableDef> {
+        self.entries.get(kind)
+    }
+}
+
+impl CollidableDef {
+    fn shapes(&self) -> Vec<Vec<Vec2>> {
+        if self.shapes.is_empty() {
+            return vec![shapes::rectangle(self.offset.into(), self.size.into())];
+        }
+        self.shapes
+            .iter()
+            .map(|shape| shape.iter().map(|&point| point.into()).collect())
+            .collect()
+    }
+
+    pub fn collidable(&self, kind: &str) -> Collidable {
+        Collidable {
+            kind: kind.to_string(),
+            size: self.size.into(),
+            offset: self.offset.into(),
+            shapes: self.shapes(),
+            lethal: self.lethal,
+            bouncy: self.bouncy,
+            updraft: self.updraft,
+        }
+    }
+
+    // The height of the sprite center above the ground, so the hitbox stands on the ground
+    pub fn ground_height(&self) -> f32 {
+        self.size.1 / 2.0 - self.offset.1
+    }
+}
+
+impl AnimationDef {
+    pub fn animation(&self, asset_server: &AssetServer) -> Animation {
+        Animation {
+            frames: self
+                .frames
+                .iter()
+                .map(|frame| asset_server.load(frame))
+                .collect(),
+            fps: self.fps,
+        }
+    }
+}
+
+// Takes over the catalogue when it was loaded or changed. All tiles are despawned,
+// so they are spawned again with the new collidables.
+fn update_catalogue(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<CollidableCatalogue>>,
+    assets: Res<Assets<CollidableCatalogue>>,
+    handles: Res<RonHandles<CollidableCatalogue>>,
+    tile_query: Query<(Entity, &Tile)>,
+) {
+    let take_over = |commands: &mut Commands, _, catalogue: &CollidableCatalogue| {
+        commands.insert_resource(catalogue.clone());
+        None
+    };
+    reload(
+        &mut commands,
+        &mut asset_events,
+        &assets,
+        &handles,
+        &tile_query,
+        take_over,
+    );
+}
+
+fn animate_collidables(time: Res<Time>, mut query: Query<(&Animation, &mut Handle<Image>)>) {
+    for (animation, mut texture) in query.iter_mut() {
+        let frame = (time.elapsed_seconds() * animation.fps) as usize % animation.frames.len();
+        if *texture != animation.frames[frame] {
+            *texture = animation.frames[frame].clone();
+        }
+    }
+}
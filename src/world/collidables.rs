@@ -1,196 +1,152 @@
-use bevy::{app::AppExit, prelude::*, sprite::collide_aabb::collide};
+use bevy::{prelude::*, sprite::collide_aabb::Collision};
+use rand::prelude::*;
 
-use crate::{stork::Stork, PIXELS_PER_METER};
+use crate::{load_texture, shapes, stork::Stork, tick::TickTransform, PIXELS_PER_METER};
 
-use super::{tiles::TILE_SIZE, TileType};
+use super::{
+    catalogue::{CollidableCatalogue, CollidableDef},
+    layouts::TileLayout,
+    tiles::TILE_SIZE,
+    World,
+};
 
+// Something the stork can fly into, as described in the collidable catalogue
 #[derive(Component)]
 pub struct Collidable {
-    collidable_type: CollidableType,
+    pub kind: String,           // its name in the catalogue
+    pub size: Vec2,             // of the hitbox, in pixels
+    pub offset: Vec2,           // of the hitbox from the center of the sprite, in pixels
+    pub shapes: Vec<Vec<Vec2>>, // convex polygons around the center of the sprite, in pixels
+    pub lethal: bool,
+    pub bouncy: bool,
+    pub updraft: f32, // in meters per second, see the catalogue
 }
 
-pub enum CollidableType {
-    House1,
-    House2,
-    House3,
+impl Collidable {
+    pub fn hitbox_center(&self, transform: &Transform) -> Vec3 {
+        transform.translation + self.offset.extend(0.0)
+    }
+
+    // The shapes where the collidable stands, in pixel coordinates
+    pub fn world_shapes<'a>(
+        &'a self,
+        transform: &'a Transform,
+    ) -> impl Iterator<Item = Vec<Vec2>> + 'a {
+        self.shapes
+            .iter()
+            .map(|shape| shapes::transformed(shape, transform))
+    }
+
+    // How `polygon` is pushed out of the shape it overlaps the most, together with that shape
+    pub fn hit(&self, transform: &Transform, polygon: &[Vec2]) -> Option<(Vec2, Vec<Vec2>)> {
+        self.world_shapes(transform)
+            .filter_map(|shape| Some((shapes::penetration(polygon, &shape)?, shape)))
+            .max_by(|(a, _), (b, _)| a.length_squared().total_cmp(&b.length_squared()))
+    }
+}
+
+// A house that is waiting for a baby. Its roof is where the bundle should land.
+#[derive(Component)]
+pub struct DeliveryTarget {
+    pub tile_nr: i64,
+    pub house: usize, // the place of the house on its tile, in the order the layout placed them
+}
+
+// Sent every simulation tick the stork overlaps a collidable, so a frame can have several of them or none
+pub struct StorkCollision {
+    pub collidable: Entity,
+    pub kind: String,
+    pub side: Collision, // the side of the collidable that the stork flew into
+    pub push: Vec2,      // the shortest move of the stork out of the collidable, in pixels
+    pub contact_point: Vec2, // in pixel coordinates, the center of where the bounding boxes of both shapes overlap
 }
 
-const SIZE_STORK: Vec2 = Vec2 { x: 64.0, y: 32.0 };
-const HOUSE_1_SIZE: Vec2 = Vec2 { x: 156.0, y: 62.0 };
-const HOUSE_2_SIZE: Vec2 = Vec2 { x: 120.0, y: 144.0 };
-const HOUSE_3_SIZE: Vec2 = Vec2 { x: 152.0, y: 310.0 };
+const TARGET_COLOR: Color = Color::rgb(1.0, 0.7, 0.7);
+
+// The stork collides as a box of the size of its hitbox that turns with it
+pub fn stork_shape(stork: &Stork, transform: &Transform) -> Vec<Vec2> {
+    shapes::transformed(&shapes::rectangle(Vec2::ZERO, stork.hitbox), transform)
+}
 
 pub fn stork_hit_collidable(
-    mut stork_query: Query<&Transform, With<Stork>>,
-    collidable_query: Query<(&Collidable, &Transform)>,
-    mut app_exit_event_writer: EventWriter<AppExit>,
+    stork_query: Query<(&Stork, &TickTransform)>,
+    collidable_query: Query<(Entity, &Collidable, &Transform)>,
+    mut collision_event_writer: EventWriter<StorkCollision>,
 ) {
-    let stork_transf = stork_query.get_single_mut().unwrap();
+    // Collide with where the stork is in the simulation, not where it is drawn
+    let (stork, tick_transform) = stork_query.get_single().unwrap();
+    let stork_shape = stork_shape(stork, &tick_transform.current);
 
-    for (collidable, collidable_transf) in collidable_query.iter() {
-        if collide(
-            stork_transf.translation,
-            SIZE_STORK,
-            collidable_transf.translation,
-            match collidable.collidable_type {
-                CollidableType::House1 => HOUSE_1_SIZE,
-                CollidableType::House2 => HOUSE_2_SIZE,
-                CollidableType::House3 => HOUSE_3_SIZE,
-            },
-        )
-        .is_some()
-        {
-            println!("Collided!");
-            println!("Game Over!");
-            app_exit_event_writer.send(AppExit);
+    for (entity, collidable, collidable_transf) in collidable_query.iter() {
+        if let Some((push, shape)) = collidable.hit(collidable_transf, &stork_shape) {
+            collision_event_writer.send(StorkCollision {
+                collidable: entity,
+                kind: collidable.kind.clone(),
+                side: shapes::side(push),
+                push,
+                contact_point: shapes::contact_point(&stork_shape, &shape),
+            });
+        }
+    }
+}
+
+// Bounces the stork off every bouncy collidable it flew into. The lethal ones end the run instead,
+// unless the stork is invulnerable: then it bounces off those too.
+pub fn bounce_off_collidables(
+    mut collision_event_reader: EventReader<StorkCollision>,
+    collidable_query: Query<&Collidable>,
+    mut stork_query: Query<&mut Stork>,
+) {
+    let mut stork = stork_query.get_single_mut().unwrap();
+    for collision in collision_event_reader.iter() {
+        if stork.perched_on(collision.collidable) {
+            continue;
+        }
+        if let Ok(collidable) = collidable_query.get(collision.collidable) {
+            if (collidable.bouncy && !collidable.lethal) || stork.is_invulnerable() {
+                stork.bounce(&collision.side);
+            }
         }
     }
 }
 
 pub fn spawn_collidables(
     commands: &mut Commands,
-    tile_type: TileType,
-    tile_nr: usize,
-    asset_server: &AssetServer,
+    layout: &TileLayout,
+    catalogue: &CollidableCatalogue,
+    world: &World,
+    tile_nr: i64,
+    asset_server: Option<&AssetServer>,
 ) -> Vec<Entity> {
+    let tile_x = tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER;
+    let crowding = world.difficulty(tile_nr as f32 * TILE_SIZE).crowding;
+    let rng = &mut world.collidable_rng(tile_nr);
     let mut collidables = Vec::new();
-    match tile_type {
-        TileType::Countryside => {}
-        TileType::Village => {
-            // one house of type house_1 will spawn on one of three possible positions
-            let house_1_pos = match tile_nr % 3 {
-                0 => Vec2::new(-300.0, HOUSE_1_SIZE.y / 2.0),
-                1 => Vec2::new(-150.0, HOUSE_1_SIZE.y / 2.0),
-                2 => Vec2::new(200.0, HOUSE_1_SIZE.y / 2.0),
-                _ => panic!("Error: Tile number out of bounds!"),
-            };
-            collidables.push(spawn_collidable(
-                commands,
-                CollidableType::House1,
-                house_1_pos + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                asset_server,
-            ));
-        }
-        TileType::CityMinor => {
-            // one house of type house_1 and one of type house_2 will spawn on one of four possible positions
-            let combination = tile_nr % 12;
-            let (option1, option2) = match combination {
-                0 => (0, 1),
-                1 => (0, 2),
-                2 => (0, 3),
-                3 => (1, 0),
-                4 => (1, 2),
-                5 => (1, 3),
-                6 => (2, 0),
-                7 => (2, 1),
-                8 => (2, 3),
-                9 => (3, 0),
-                10 => (3, 1),
-                11 => (3, 2),
-                _ => panic!("Error: Tile number out of bounds!"),
-            };
-            let house_1_pos = match option1 {
-                0 => Vec2::new(-300.0, HOUSE_1_SIZE.y / 2.0),
-                1 => Vec2::new(-150.0, HOUSE_1_SIZE.y / 2.0),
-                2 => Vec2::new(200.0, HOUSE_1_SIZE.y / 2.0),
-                3 => Vec2::new(350.0, HOUSE_1_SIZE.y / 2.0),
-                _ => panic!("Error: Tile number out of bounds!"),
-            };
-            let house_2_pos = match option2 {
-                0 => Vec2::new(-300.0, HOUSE_2_SIZE.y / 2.0),
-                1 => Vec2::new(-150.0, HOUSE_2_SIZE.y / 2.0),
-                2 => Vec2::new(200.0, HOUSE_2_SIZE.y / 2.0),
-                3 => Vec2::new(350.0, HOUSE_2_SIZE.y / 2.0),
-                _ => panic!("Error: Tile number out of bounds!"),
-            };
-            collidables.push(spawn_collidable(
-                commands,
-                CollidableType::House1,
-                house_1_pos + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                asset_server,
-            ));
-            collidables.push(spawn_collidable(
-                commands,
-                CollidableType::House2,
-                house_2_pos + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                asset_server,
-            ));
-        }
-        TileType::CityMajor => {
-            // Three houses, at least 1 of type house_2, the others of type house_2 or house_1
-            // The locations are chosen from 5 options
-            // we will use a greedy algoritm to choose the locations, with as seed the tile nr
-            let mut options = vec![
-                Vec2::new(-400.0, 0.0),
-                Vec2::new(-150.0, 0.0),
-                Vec2::new(000.0, 0.0),
-                Vec2::new(200.0, 0.0),
-                Vec2::new(350.0, 0.0),
-            ];
-            let amount_of_house_2 = (tile_nr % 3) + 1;
-            let mut house_2_locations = Vec::new();
-            for _ in 0..amount_of_house_2 {
-                let index = (tile_nr % options.len()) as usize;
-                house_2_locations.push(options.remove(index));
-            }
-            let mut house_1_locations = Vec::new();
-            for _ in 0..(3 - amount_of_house_2) {
-                let index = (tile_nr % options.len()) as usize;
-                house_1_locations.push(options.remove(index));
-            }
-            for pos in house_2_locations {
-                collidables.push(spawn_collidable(
-                    commands,
-                    CollidableType::House2,
-                    pos + Vec2::new(0.0, HOUSE_2_SIZE.y / 2.0)
-                        + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                    asset_server,
-                ));
-            }
-            for pos in house_1_locations {
-                collidables.push(spawn_collidable(
-                    commands,
-                    CollidableType::House1,
-                    pos + Vec2::new(0.0, HOUSE_1_SIZE.y / 2.0)
-                        + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                    asset_server,
-                ));
-            }
+    let mut target_candidate = None;
+    for (house, (kind, slot)) in layout.place_houses(rng, crowding).into_iter().enumerate() {
+        let Some(def) = catalogue.get(kind) else {
+            warn!("There is no {kind} in the collidable catalogue");
+            continue;
+        };
+        let pos = Vec2::new(tile_x + slot, def.ground_height());
+        let collidable = spawn_collidable(commands, kind, def, pos, asset_server);
+        if def.delivery_target && target_candidate.is_none() {
+            target_candidate = Some((collidable, house));
         }
-        TileType::CityMetropolis => {
-            // Four houses, one of type house_3, the others of type house_2
-            // The locations are chosen from 5 options
-            // we will use a greedy algoritm to choose the locations, with as seed the tile nr
-            let mut options = vec![
-                Vec2::new(-350.0, 0.0),
-                Vec2::new(-150.0, 0.0),
-                Vec2::new(000.0, 0.0),
-                Vec2::new(200.0, 0.0),
-                Vec2::new(400.0, 0.0),
-            ];
-            let house_3_location = options.remove((tile_nr % options.len()) as usize);
-            let mut house_2_locations = Vec::new();
-            for _ in 0..3 {
-                let index = (tile_nr % options.len()) as usize;
-                house_2_locations.push(options.remove(index));
-            }
-            collidables.push(spawn_collidable(
-                commands,
-                CollidableType::House3,
-                house_3_location
-                    + Vec2::new(0.0, HOUSE_3_SIZE.y / 2.0)
-                    + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                asset_server,
+        collidables.push(collidable);
+    }
+    // Half of the built-up tiles have one house that is waiting for a delivery, until it got its baby
+    if rng.gen_bool(0.5) {
+        if let Some((target, house)) =
+            target_candidate.filter(|&(_, house)| !world.is_delivered(tile_nr, house))
+        {
+            commands.entity(target).insert((
+                DeliveryTarget { tile_nr, house },
+                Sprite {
+                    color: TARGET_COLOR,
+                    ..Default::default()
+                },
             ));
-            for pos in house_2_locations {
-                collidables.push(spawn_collidable(
-                    commands,
-                    CollidableType::House2,
-                    pos + Vec2::new(0.0, HOUSE_2_SIZE.y / 2.0)
-                        + Vec2::new(tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER, 0.0),
-                    asset_server,
-                ));
-            }
         }
     }
     collidables
@@ -198,22 +154,21 @@ pub fn spawn_collidables(
 
 pub fn spawn_collidable(
     commands: &mut Commands,
-    collidable_type: CollidableType,
+    kind: &str,
+    def: &CollidableDef,
     pos: Vec2,
-    asset_server: &AssetServer,
+    asset_server: Option<&AssetServer>,
 ) -> Entity {
-    commands
-        .spawn((
-            SpriteBundle {
-                transform: Transform::from_translation(Vec3::new(pos.x, pos.y, -0.5)),
-                texture: match collidable_type {
-                    CollidableType::House1 => asset_server.load("sprites/collidables/house_1.png"),
-                    CollidableType::House2 => asset_server.load("sprites/collidables/house_2.png"),
-                    CollidableType::House3 => asset_server.load("sprites/collidables/house_3.png"),
-                },
-                ..Default::default()
-            },
-            Collidable { collidable_type },
-        ))
-        .id()
+    let mut collidable = commands.spawn((
+        SpriteBundle {
+            transform: Transform::from_translation(Vec3::new(pos.x, pos.y, def.z)),
+            texture: load_texture(asset_server, &def.sprite),
+            ..Default::default()
+        },
+        def.collidable(kind),
+    ));
+    if let (Some(animation), Some(asset_server)) = (&def.animation, asset_server) {
+        collidable.insert(animation.animation(asset_server));
+    }
+    collidable.id()
 }
@@ -0,0 +1,156 @@
+use bevy::{prelude::*, reflect::TypeUuid};
+use serde::Deserialize;
+
+use super::{
+    ron_asset::{reload, RonAsset, RonAssetPlugin, RonHandles},
+    tiles::Tile,
+    World,
+};
+
+// How hard the world is at some distance from the start
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DifficultyLevel {
+    pub city_bias: f32, // added to the density of the world, so big cities come up more often
+    pub crowding: f32, // the chance that a free slot on a tile gets a house anyway, closing the gaps
+    pub wind_scale: f32, // how much stronger the wind blows
+}
+
+// The difficulty curve from assets/world.difficulty.ron, as levels at distances from the start. In between,
+// one level blends into the next, beyond the last one the world stays as hard as that. The world of a run is
+// made with the curve that is the resource when the run starts. Until it is loaded the curve is empty, and the
+// world doesn't get any harder.
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug, Default)]
+#[uuid = "4d8a2f61-97c3-4e0b-a5d1-3b6e9c2f7a80"]
+#[serde(transparent)]
+pub struct Difficulty {
+    curve: Vec<(f32, DifficultyLevel)>, // by distance in meters, in order
+}
+
+// The world as it is without any difficulty
+const BASE_LEVEL: DifficultyLevel = DifficultyLevel {
+    city_bias: 0.0,
+    crowding: 0.0,
+    wind_scale: 1.0,
+};
+
+const DIFFICULTY_PATH: &str = "world.difficulty.ron";
+
+// Loads the difficulty curve through the asset server and hot reloads it
+pub struct DifficultyPlugin;
+
+impl Plugin for DifficultyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<Difficulty>::new([
+            DIFFICULTY_PATH.to_string()
+        ]))
+        .init_resource::<Difficulty>()
+        .add_system(update_difficulty);
+    }
+}
+
+impl DifficultyLevel {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            city_bias: mix(self.city_bias, other.city_bias),
+            crowding: mix(self.crowding, other.crowding),
+            wind_scale: mix(self.wind_scale, other.wind_scale),
+        }
+    }
+}
+
+impl RonAsset for Difficulty {
+    const EXTENSIONS: &'static [&'static str] = &["difficulty.ron"];
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let difficulty: Self = ron::de::from_bytes(bytes).map_err(|err| err.to_string())?;
+        if difficulty.curve.is_empty() {
+            return Err("the curve has no levels".to_string());
+        }
+        for pair in difficulty.curve.windows(2) {
+            if pair[1].0 <= pair[0].0 {
+                return Err(format!(
+                    "the level at {} m comes after the one at {} m",
+                    pair[1].0, pair[0].0
+                ));
+            }
+        }
+        for (at, level) in &difficulty.curve {
+            if !(0.0..=1.0).contains(&level.crowding) {
+                return Err(format!("the crowding at {at} m is not a chance"));
+            }
+            if level.wind_scale < 0.0 {
+                return Err(format!("the wind at {at} m blows backwards"));
+            }
+        }
+        Ok(difficulty)
+    }
+}
+
+impl Difficulty {
+    // Reads the curve straight from the asset folder, for when there is no asset server
+    pub fn read() -> Result<Self, String> {
+        Self::read_from(DIFFICULTY_PATH)
+    }
+
+    pub fn loaded(&self) -> bool {
+        !self.curve.is_empty()
+    }
+
+    // The level at `x`, in meters. The world gets harder the same way in both directions.
+    pub fn at(&self, x: f32) -> DifficultyLevel {
+        let distance = x.abs();
+        let next = self.curve.partition_point(|&(at, _)| at <= distance);
+        match (self.curve.get(next.wrapping_sub(1)), self.curve.get(next)) {
+            (Some(&(from, before)), Some(&(to, after))) => {
+                before.lerp(&after, (distance - from) / (to - from))
+            }
+            (Some(&(_, level)), None) | (None, Some(&(_, level))) => level,
+            (None, None) => BASE_LEVEL,
+        }
+    }
+}
+
+// Takes over the curve when it was loaded or changed, also for the world of the run in progress. All tiles
+// are despawned, so they are spawned again as hard as the new curve makes them.
+fn update_difficulty(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<Difficulty>>,
+    assets: Res<Assets<Difficulty>>,
+    handles: Res<RonHandles<Difficulty>>,
+    mut difficulty: ResMut<Difficulty>,
+    mut world: ResMut<World>,
+    tile_query: Query<(Entity, &Tile)>,
+) {
+    let take_over = |_: &mut Commands, _, changed: &Difficulty| {
+        *difficulty = changed.clone();
+        world.set_difficulty(changed.clone());
+        None
+    };
+    reload(
+        &mut commands,
+        &mut asset_events,
+        &assets,
+        &handles,
+        &tile_query,
+        take_over,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_out_of_order_or_empty_are_rejected() {
+        assert!(Difficulty::read().unwrap().loaded());
+        assert!(Difficulty::from_bytes(b"[]").is_err());
+        let level = "(city_bias: 0.0, crowding: 0.0, wind_scale: 1.0)";
+        let ascending = format!("[(0.0, {level}), (100.0, {level})]");
+        assert!(Difficulty::from_bytes(ascending.as_bytes()).is_ok());
+        let descending = format!("[(100.0, {level}), (0.0, {level})]");
+        assert!(Difficulty::from_bytes(descending.as_bytes()).is_err());
+        let repeated = format!("[(0.0, {level}), (0.0, {level})]");
+        assert!(Difficulty::from_bytes(repeated.as_bytes()).is_err());
+    }
+}
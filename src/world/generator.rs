@@ -0,0 +1,119 @@
+use super::{
+    difficulty::Difficulty,
+    splitmix,
+    tiles::{TileType, TILE_SIZE},
+};
+
+// Decides what kind of place every tile is. It may only depend on the seed it was made with, the difficulty
+// and the tile number, so a tile that is generated again comes out the same.
+pub trait WorldGenerator: Send + Sync {
+    fn tile_type(&self, tile_nr: i64, difficulty: &Difficulty) -> TileType;
+}
+
+// From empty to crowded. Neighbouring tiles are never more than one step apart, so between the countryside and
+// a village there are always outskirts, and between a village and the city there are suburbs.
+const DENSITIES: [TileType; 7] = [
+    TileType::Countryside,
+    TileType::Outskirts,
+    TileType::Village,
+    TileType::Suburbs,
+    TileType::CityMinor,
+    TileType::CityMajor,
+    TileType::CityMetropolis,
+];
+
+// The layers of noise, as (cell size in tiles, weight). The weights add up to 1.
+// The density changes by at most 1.5 * weight / cell size per tile for each layer, so together with the contrast
+// it stays below one step of DENSITIES per tile.
+const OCTAVES: [(i64, f32); 3] = [(32, 0.5), (16, 0.3), (8, 0.2)];
+// Spreads the density out from the middle, where the layers add up most of the time
+const CONTRAST: f32 = 1.5;
+// Where the noise layers number their random values, clear of the per tile streams of the world
+const FIRST_NOISE_STREAM: u64 = 16;
+
+// Builds up the density of the world from layers of seeded value noise: random values at regular cells,
+// smoothly blended in between. The big cells make cities and countryside, the small ones vary them.
+// Further out, the difficulty pushes the density up.
+pub struct NoiseGenerator {
+    seed: u64,
+}
+
+impl NoiseGenerator {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    // Between 0 (empty) and 1 (crowded)
+    fn density(&self, tile_nr: i64, difficulty: &Difficulty) -> f32 {
+        let noise: f32 = OCTAVES
+            .iter()
+            .enumerate()
+            .map(|(octave, &(cell_size, weight))| {
+                let cell = tile_nr.div_euclid(cell_size);
+                let t = tile_nr.rem_euclid(cell_size) as f32 / cell_size as f32;
+                let t = t * t * (3.0 - 2.0 * t);
+                let (left, right) = (
+                    self.lattice(octave as u64, cell),
+                    self.lattice(octave as u64, cell + 1),
+                );
+                weight * (left + (right - left) * t)
+            })
+            .sum();
+        let city_bias = difficulty.at(tile_nr as f32 * TILE_SIZE).city_bias;
+        ((noise - 0.5) * CONTRAST + 0.5 + city_bias).clamp(0.0, 1.0)
+    }
+
+    // The random value at the start of a cell, between 0 and 1
+    fn lattice(&self, octave: u64, cell: i64) -> f32 {
+        let hash = splitmix(
+            self.seed
+                ^ (cell as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (FIRST_NOISE_STREAM + octave).wrapping_mul(0xD1B5_4A32_D192_ED03),
+        );
+        (hash >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+impl WorldGenerator for NoiseGenerator {
+    fn tile_type(&self, tile_nr: i64, difficulty: &Difficulty) -> TileType {
+        let level = (self.density(tile_nr, difficulty) * DENSITIES.len() as f32) as usize;
+        DENSITIES[level.min(DENSITIES.len() - 1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn level(tile_type: TileType) -> usize {
+        DENSITIES.iter().position(|&t| t == tile_type).unwrap()
+    }
+
+    proptest! {
+        #[test]
+        fn neighbours_are_at_most_one_step_apart(seed: u64, start in -1_000_000i64..1_000_000) {
+            let generator = NoiseGenerator::new(seed);
+            let difficulty = Difficulty::read().unwrap();
+            for tile_nr in start..start + 200 {
+                let (a, b) = (
+                    generator.tile_type(tile_nr, &difficulty),
+                    generator.tile_type(tile_nr + 1, &difficulty),
+                );
+                prop_assert!(level(a).abs_diff(level(b)) <= 1, "{tile_nr}: {a:?} next to {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn every_density_occurs() {
+        let generator = NoiseGenerator::new(7);
+        let difficulty = Difficulty::read().unwrap();
+        for tile_type in DENSITIES {
+            assert!(
+                (-5000..5000).any(|tile_nr| generator.tile_type(tile_nr, &difficulty) == tile_type),
+                "{tile_type:?}"
+            );
+        }
+    }
+}
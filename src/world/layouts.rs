@@ -0,0 +1,159 @@
+use bevy::{prelude::*, reflect::TypeUuid, utils::HashMap};
+use rand::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    ron_asset::{reload, RonAsset, RonAssetPlugin, RonHandles},
+    tiles::{Tile, TileType},
+};
+
+// What a tile looks like and where its houses may stand, from assets/tiles/<tile type>.tile.ron
+#[derive(Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "6f1e4c1a-3d55-4b8e-9a3c-52c0d7e1f2a4"]
+pub struct TileLayout {
+    pub background: String, // the sprite, relative to assets/
+    slots: Vec<f32>,        // where a house can stand, in pixels from the center of the tile
+    houses: Vec<Houses>,    // placed in order, each house on a free slot
+}
+
+// Between `min` and `max` houses, each of a kind picked from `kinds`
+#[derive(Deserialize, Clone, Debug)]
+struct Houses {
+    kinds: Vec<String>, // names in the collidable catalogue
+    min: usize,
+    max: usize,
+}
+
+// The layouts that tiles are spawned from. A tile type without a layout is not spawned yet.
+#[derive(Resource, Default)]
+pub struct TileLayouts {
+    layouts: HashMap<TileType, TileLayout>,
+}
+
+// Loads the layouts through the asset server, and hot reloads them when their files change
+pub struct TileLayoutPlugin;
+
+impl Plugin for TileLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RonAssetPlugin::<TileLayout>::new(
+            TILE_TYPES.into_iter().map(layout_path),
+        ))
+        .add_system(update_tile_layouts);
+    }
+}
+
+const TILE_TYPES: [TileType; 7] = [
+    TileType::Countryside,
+    TileType::Outskirts,
+    TileType::Village,
+    TileType::Suburbs,
+    TileType::CityMinor,
+    TileType::CityMajor,
+    TileType::CityMetropolis,
+];
+
+fn layout_path(tile_type: TileType) -> String {
+    let name = match tile_type {
+        TileType::Countryside => "countryside",
+        TileType::Outskirts => "outskirts",
+        TileType::Village => "village",
+        TileType::Suburbs => "suburbs",
+        TileType::CityMinor => "city_minor",
+        TileType::CityMajor => "city_major",
+        TileType::CityMetropolis => "city_metropolis",
+    };
+    format!("tiles/{name}.tile.ron")
+}
+
+impl RonAsset for TileLayout {
+    const EXTENSIONS: &'static [&'static str] = &["tile.ron"];
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let layout: Self = ron::de::from_bytes(bytes).map_err(|err| err.to_string())?;
+        for houses in &layout.houses {
+            if houses.kinds.is_empty() || houses.min > houses.max {
+                return Err(format!("{houses:?} can never place a house"));
+            }
+        }
+        let most_houses: usize = layout.houses.iter().map(|houses| houses.max).sum();
+        if most_houses > layout.slots.len() {
+            return Err(format!(
+                "up to {most_houses} houses do not fit on {} slots",
+                layout.slots.len()
+            ));
+        }
+        Ok(layout)
+    }
+}
+
+impl TileLayout {
+    // The kind and horizontal offset of every house on one tile. With `crowding`, the slots that are left
+    // over may get a house too, of any kind this layout has.
+    pub fn place_houses(&self, rng: &mut StdRng, crowding: f32) -> Vec<(&str, f32)> {
+        let mut free_slots = self.slots.clone();
+        let mut placed = Vec::new();
+        for houses in &self.houses {
+            for _ in 0..rng.gen_range(houses.min..=houses.max) {
+                let slot = free_slots.remove(rng.gen_range(0..free_slots.len()));
+                placed.push((houses.kinds.choose(rng).unwrap().as_str(), slot));
+            }
+        }
+        let kinds: Vec<&str> = self
+            .houses
+            .iter()
+            .flat_map(|houses| houses.kinds.iter().map(String::as_str))
+            .collect();
+        for slot in free_slots {
+            if !kinds.is_empty() && rng.gen::<f32>() < crowding {
+                placed.push((*kinds.choose(rng).unwrap(), slot));
+            }
+        }
+        placed
+    }
+}
+
+impl TileLayouts {
+    // Reads the layouts straight from the asset folder, for when there is no asset server
+    pub fn read() -> Result<Self, String> {
+        let mut layouts = HashMap::default();
+        for tile_type in TILE_TYPES {
+            layouts.insert(tile_type, TileLayout::read_from(&layout_path(tile_type))?);
+        }
+        Ok(Self { layouts })
+    }
+
+    pub fn get(&self, tile_type: TileType) -> Option<&TileLayout> {
+        self.layouts.get(&tile_type)
+    }
+
+    pub fn loaded(&self) -> bool {
+        TILE_TYPES
+            .iter()
+            .all(|tile_type| self.layouts.contains_key(tile_type))
+    }
+}
+
+// Takes over every layout that was loaded or changed. The tiles of a changed layout are despawned,
+// so they are spawned again from the new layout.
+fn update_tile_layouts(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<TileLayout>>,
+    assets: Res<Assets<TileLayout>>,
+    handles: Res<RonHandles<TileLayout>>,
+    mut tile_layouts: ResMut<TileLayouts>,
+    tile_query: Query<(Entity, &Tile)>,
+) {
+    let take_over = |_: &mut Commands, index: usize, layout: &TileLayout| {
+        let tile_type = TILE_TYPES[index];
+        tile_layouts.layouts.insert(tile_type, layout.clone());
+        Some(tile_type)
+    };
+    reload(
+        &mut commands,
+        &mut asset_events,
+        &assets,
+        &handles,
+        &tile_query,
+        take_over,
+    );
+}
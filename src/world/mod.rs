@@ -1,48 +1,211 @@
+pub mod cache;
+pub mod catalogue;
 pub mod collidables;
+pub mod difficulty;
+pub mod generator;
+pub mod layouts;
+pub mod ron_asset;
 pub mod tiles;
+pub mod weather;
+pub mod wind;
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashSet};
 
 use rand::prelude::*;
-use std::collections::HashMap;
 
-use self::tiles::TileType;
+use self::{
+    cache::TileCache,
+    difficulty::{Difficulty, DifficultyLevel},
+    generator::{NoiseGenerator, WorldGenerator},
+    tiles::TileType,
+    weather::{Conditions, Weather, STRETCH_LENGTH},
+    wind::TileWind,
+};
 
 #[derive(Resource)]
 pub struct World {
     seed: u64,
-    tiles: HashMap<usize, TileType>,
+    generator: Box<dyn WorldGenerator>,
+    difficulty: Difficulty,
+    tiles: TileCache<TileType>, // by tile number, negative to the left of the origin
+    winds: TileCache<TileWind>,
+    weathers: TileCache<Weather>, // per stretch
+    // The houses that got their baby, by tile number and place on the tile. Unlike the caches this is never
+    // forgotten, so a house that comes back into view doesn't wait for a baby again.
+    delivered: HashSet<(i64, usize)>,
 }
 
 impl Default for World {
     fn default() -> Self {
+        Self::new(random::<u64>(), Difficulty::default())
+    }
+}
+
+// The random streams that are derived per tile. Each one is independent of the others.
+const STREAM_COLLIDABLES: u64 = 1;
+const STREAM_WIND: u64 = 2;
+const STREAM_WEATHER: u64 = 3; // per stretch instead of per tile
+
+// How many tiles and stretches are remembered. Far more than are ever in view, so a tile is only generated
+// again when the stork comes back to it from far away.
+const TILE_CACHE_SIZE: usize = 64;
+const STRETCH_CACHE_SIZE: usize = 8;
+
+impl World {
+    pub fn new(seed: u64, difficulty: Difficulty) -> Self {
+        Self::with_generator(seed, Box::new(NoiseGenerator::new(seed)), difficulty)
+    }
+
+    pub fn with_generator(
+        seed: u64,
+        generator: Box<dyn WorldGenerator>,
+        difficulty: Difficulty,
+    ) -> Self {
         Self {
-            seed: random::<u64>(),
-            tiles: HashMap::new(),
+            seed,
+            generator,
+            difficulty,
+            tiles: TileCache::new(TILE_CACHE_SIZE),
+            winds: TileCache::new(TILE_CACHE_SIZE),
+            weathers: TileCache::new(STRETCH_CACHE_SIZE),
+            delivered: HashSet::default(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn get_tile(&mut self, tile_nr: i64) -> TileType {
+        if let Some(tile) = self.tiles.get(tile_nr) {
+            return tile;
         }
+        let tile = self.generator.tile_type(tile_nr, &self.difficulty);
+        self.tiles.insert(tile_nr, tile);
+        tile
+    }
+
+    // How hard the world is at `x`, in meters
+    pub fn difficulty(&self, x: f32) -> DifficultyLevel {
+        self.difficulty.at(x)
+    }
+
+    // Everything generated from the old curve is forgotten, so it is generated again from the new one
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.tiles.clear();
+        self.winds.clear();
+    }
+
+    pub fn is_delivered(&self, tile_nr: i64, house: usize) -> bool {
+        self.delivered.contains(&(tile_nr, house))
+    }
+
+    pub fn deliver(&mut self, tile_nr: i64, house: usize) {
+        self.delivered.insert((tile_nr, house));
+    }
+
+    // The random generator that places the collidables on a tile. It gives the same layout every time the tile is spawned.
+    pub fn collidable_rng(&self, tile_nr: i64) -> StdRng {
+        self.tile_rng(tile_nr, STREAM_COLLIDABLES)
+    }
+
+    // How the air moves over a tile. It is the same every time the stork passes by.
+    pub fn tile_wind(&mut self, tile_nr: i64) -> TileWind {
+        if let Some(wind) = self.winds.get(tile_nr) {
+            return wind;
+        }
+        let tile_type = self.get_tile(tile_nr);
+        let wind = TileWind::generate(tile_nr, tile_type, &mut self.tile_rng(tile_nr, STREAM_WIND));
+        self.winds.insert(tile_nr, wind);
+        wind
+    }
+
+    // The weather and the light at `x`, in meters
+    pub fn conditions(&mut self, x: f32) -> Conditions {
+        let stretch = (x / STRETCH_LENGTH).floor() as i64;
+        let weather = match self.weathers.get(stretch) {
+            Some(weather) => weather,
+            None => {
+                let weather =
+                    Weather::generate(stretch, &mut self.tile_rng(stretch, STREAM_WEATHER));
+                self.weathers.insert(stretch, weather);
+                weather
+            }
+        };
+        Conditions::at(x, weather)
+    }
+
+    fn tile_rng(&self, tile_nr: i64, stream: u64) -> StdRng {
+        // Negative tile numbers wrap around to seeds of their own
+        StdRng::seed_from_u64(splitmix(
+            self.seed
+                ^ (tile_nr as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ stream.wrapping_mul(0xD1B5_4A32_D192_ED03),
+        ))
     }
 }
 
-impl World {
-    pub fn get_tile(&mut self, tile_nr: usize) -> TileType {
-        if let Some(tile) = self.tiles.get(&tile_nr) {
-            return *tile;
+// The splitmix64 finalizer, so neighbouring tiles and streams get unrelated seeds
+fn splitmix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::layouts::TileLayouts;
+
+    // Everything that decides where the collidables of a tile stand, as bytes
+    fn collidable_layout(world: &mut World, layouts: &TileLayouts, tile_nr: i64) -> Vec<u8> {
+        let tile_type = world.get_tile(tile_nr);
+        let mut bytes = format!("{tile_type:?}").into_bytes();
+        let layout = layouts.get(tile_type).unwrap();
+        let crowding = world.difficulty(tile_nr as f32 * tiles::TILE_SIZE).crowding;
+        for (kind, slot) in layout.place_houses(&mut world.collidable_rng(tile_nr), crowding) {
+            bytes.extend(kind.as_bytes());
+            bytes.extend(slot.to_le_bytes());
         }
-        let micro_fluctuations = 1.5 * (tile_nr as f32).sin().powi(2);
-        let minor_fluctiations = 1.0 * (tile_nr as f32 * 0.42).sin().powi(4);
-        let major_fluctiations = 2.5 * (tile_nr as f32 * 0.13).sin().powi(8);
-        let sum = micro_fluctuations + minor_fluctiations + major_fluctiations;
-        self.tiles.insert(
-            tile_nr,
-            match sum {
-                x if x >= 0.0 && x <= 1.0 => TileType::Countryside,
-                x if x > 1.0 && x <= 2.0 => TileType::Village,
-                x if x > 2.0 && x <= 3.0 => TileType::CityMinor,
-                x if x > 3.0 && x <= 4.0 => TileType::CityMajor,
-                x if x > 4.0 && x <= 5.0 => TileType::CityMetropolis,
-                _ => panic!("Error: Tile type out of bounds!"),
-            },
-        );
-        self.tiles[&tile_nr]
+        bytes
+    }
+
+    #[test]
+    fn evicted_tiles_come_back_identical() {
+        let layouts = TileLayouts::read().unwrap();
+        let mut world = World::new(42, Difficulty::read().unwrap());
+        let near: Vec<i64> = (-20..20).collect();
+        let before: Vec<Vec<u8>> = near
+            .iter()
+            .map(|&tile_nr| collidable_layout(&mut world, &layouts, tile_nr))
+            .collect();
+        let winds_before: Vec<String> = near
+            .iter()
+            .map(|&tile_nr| format!("{:?}", world.tile_wind(tile_nr)))
+            .collect();
+
+        // Fly far away, so every tile near the origin is evicted
+        for tile_nr in 10_000..10_000 + 4 * TILE_CACHE_SIZE as i64 {
+            world.get_tile(tile_nr);
+            world.tile_wind(tile_nr);
+            world.conditions(tile_nr as f32 * tiles::TILE_SIZE);
+        }
+        assert_eq!(world.tiles.len(), TILE_CACHE_SIZE);
+        assert_eq!(world.winds.len(), TILE_CACHE_SIZE);
+        assert!(near
+            .iter()
+            .all(|&tile_nr| world.tiles.get(tile_nr).is_none()));
+
+        let after: Vec<Vec<u8>> = near
+            .iter()
+            .map(|&tile_nr| collidable_layout(&mut world, &layouts, tile_nr))
+            .collect();
+        let winds_after: Vec<String> = near
+            .iter()
+            .map(|&tile_nr| format!("{:?}", world.tile_wind(tile_nr)))
+            .collect();
+        assert_eq!(before, after);
+        assert_eq!(winds_before, winds_after);
     }
 }
@@ -0,0 +1,112 @@
+use bevy::{
+    asset::{Asset, AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+
+use std::marker::PhantomData;
+
+use crate::read_asset;
+
+use super::tiles::{Tile, TileType};
+
+// A RON file in the asset folder that is checked when it is read, like the tile layouts or the catalogue
+pub trait RonAsset: Asset + Clone {
+    const EXTENSIONS: &'static [&'static str];
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, String>;
+
+    // Reads the file at `path` straight from the asset folder, for when there is no asset server
+    fn read_from(path: &str) -> Result<Self, String> {
+        Self::from_bytes(&read_asset(path)?).map_err(|err| format!("{path}: {err}"))
+    }
+}
+
+// Loads the files at `paths` through the asset server, and keeps them loaded so they are hot reloaded
+pub struct RonAssetPlugin<T> {
+    paths: Vec<String>,
+    asset: PhantomData<fn() -> T>,
+}
+
+// The handles that keep the files loaded, in the order of their paths
+#[derive(Resource)]
+pub struct RonHandles<T: RonAsset>(Vec<Handle<T>>);
+
+struct RonLoader<T>(PhantomData<fn() -> T>);
+
+impl<T> RonAssetPlugin<T> {
+    pub fn new(paths: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            paths: paths.into_iter().collect(),
+            asset: PhantomData,
+        }
+    }
+}
+
+impl<T: RonAsset> Plugin for RonAssetPlugin<T> {
+    fn build(&self, app: &mut App) {
+        let paths = self.paths.clone();
+        app.add_asset::<T>()
+            .init_asset_loader::<RonLoader<T>>()
+            .add_startup_system(
+                move |mut commands: Commands, asset_server: Res<AssetServer>| {
+                    let handles = paths.iter().map(|path| asset_server.load(path.as_str()));
+                    commands.insert_resource(RonHandles::<T>(handles.collect()));
+                },
+            );
+    }
+}
+
+impl<T> Default for RonLoader<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: RonAsset> AssetLoader for RonLoader<T> {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let asset = T::from_bytes(bytes).map_err(bevy::asset::Error::msg)?;
+            load_context.set_default_asset(LoadedAsset::new(asset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
+
+// Hands every file that was loaded or changed to `take_over`, with the place of its path in the plugin.
+// The tiles that were spawned from the old file are despawned, so they are spawned again from the new one:
+// the tiles of the type that `take_over` returns, or all of them if it returns none.
+pub fn reload<T: RonAsset>(
+    commands: &mut Commands,
+    asset_events: &mut EventReader<AssetEvent<T>>,
+    assets: &Assets<T>,
+    handles: &RonHandles<T>,
+    tile_query: &Query<(Entity, &Tile)>,
+    mut take_over: impl FnMut(&mut Commands, usize, &T) -> Option<TileType>,
+) {
+    for event in asset_events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let (Some(index), Some(asset)) = (
+            handles.0.iter().position(|h| h == handle),
+            assets.get(handle),
+        ) else {
+            continue;
+        };
+        let tile_type = take_over(commands, index, asset);
+        for (entity, tile) in tile_query.iter() {
+            if tile_type.is_none_or(|tile_type| tile.tile_type == tile_type) {
+                tile.despawn(commands, entity);
+            }
+        }
+    }
+}
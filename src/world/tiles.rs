@@ -2,86 +2,112 @@ use bevy::prelude::*;
 
 use std::collections::HashSet;
 
-use crate::PIXELS_PER_METER;
+use crate::{load_texture, stork::Stork, PIXELS_PER_METER};
 
-use super::{collidables::spawn_collidables, World};
+use super::{
+    catalogue::CollidableCatalogue, collidables::spawn_collidables, layouts::TileLayouts, World,
+};
 
 #[derive(Component)]
 pub struct Tile {
-    tile_type: TileType,
-    order: usize,
+    pub tile_type: TileType,
+    order: i64, // the tile number, negative to the left of the origin
     collidables: Vec<Entity>,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TileType {
     Countryside,
+    Outskirts,
     Village,
+    Suburbs,
     CityMinor,
     CityMajor,
     CityMetropolis,
 }
 
+impl Tile {
+    // Despawns the tile together with its collidables
+    pub fn despawn(&self, commands: &mut Commands, entity: Entity) {
+        commands.entity(entity).despawn();
+        for collidable in self.collidables.iter() {
+            commands.entity(*collidable).despawn();
+        }
+    }
+}
+
 pub const TILE_SIZE: f32 = 10.0; // in meters
 pub const MAX_WINDOW_WIDTH: f32 = 2000.0; // in pixels
+const DESPAWN_MARGIN: i64 = 1; // in tiles
 
-// Spawns tiles when the camera moves into a region where there are no tyles yet
+// Spawns tiles when the stork moves into a region where there are no tyles yet.
+// The camera never strays far from the stork, so this covers everything that could be visible.
 pub fn spawn_tiles(
     mut commands: Commands,
     mut world: ResMut<World>,
     mut tile_query: Query<(&Tile, Entity)>,
-    camera: Query<&Transform, With<Camera>>,
-    asset_server: Res<AssetServer>,
+    stork_query: Query<&Stork>,
+    tile_layouts: Res<TileLayouts>,
+    catalogue: Option<Res<CollidableCatalogue>>,
+    asset_server: Option<Res<AssetServer>>,
 ) {
-    // get the camera
-    let camera = camera.get_single().unwrap();
+    let asset_server = asset_server.as_deref();
+    let stork_x = stork_query.get_single().unwrap().position.x * PIXELS_PER_METER;
     // Find all tile positions that could be visible
-    let right_bound = camera.translation.x + MAX_WINDOW_WIDTH; // The right bound of the camera in pixel coordinates
-    let left_bound = camera.translation.x - MAX_WINDOW_WIDTH; // The left bound of the camera in pixel coordinates
-    let right_tile = (right_bound / PIXELS_PER_METER / TILE_SIZE).ceil() as usize; // The right bound of the camera in tile coordinates
-    let left_tile = (left_bound / PIXELS_PER_METER / TILE_SIZE).floor() as usize; // The left bound of the camera in tile coordinates
-                                                                                  // Check all tiles. Despawn those that are too far away. Spawn the missing tiles.
+    let right_bound = stork_x + MAX_WINDOW_WIDTH; // The right bound of what could be visible, in pixel coordinates
+    let left_bound = stork_x - MAX_WINDOW_WIDTH; // The left bound of what could be visible, in pixel coordinates
+    let right_tile = (right_bound / PIXELS_PER_METER / TILE_SIZE).ceil() as i64; // The right bound in tile coordinates
+    let left_tile = (left_bound / PIXELS_PER_METER / TILE_SIZE).floor() as i64; // The left bound in tile coordinates
+
+    // Check all tiles. Despawn those that are too far away on either side. Spawn the missing tiles.
+    // A tile is only despawned a tile beyond the bounds, so turning around at a tile edge doesn't respawn it.
     let mut present_tiles = HashSet::new();
     for (tile, entity) in tile_query.iter_mut() {
-        let tile_nr = tile.order;
-        if tile.order < left_tile {
-            // Don't despawn tiles to the right
-            commands.entity(entity).despawn();
-            for collidable in tile.collidables.iter() {
-                commands.entity(*collidable).despawn();
-            }
+        if tile.order < left_tile - DESPAWN_MARGIN || tile.order > right_tile + DESPAWN_MARGIN {
+            tile.despawn(&mut commands, entity);
         } else {
-            present_tiles.insert(tile_nr);
+            present_tiles.insert(tile.order);
         }
     }
     for tile_nr in left_tile..=right_tile {
-        if !present_tiles.contains(&tile_nr) {
-            let tyle_type = world.get_tile(tile_nr);
-            let collidables = spawn_collidables(&mut commands, tyle_type, tile_nr, &asset_server);
-            commands.spawn((
-                SpriteBundle {
-                    transform: Transform::from_translation(Vec3::new(
-                        tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER,
-                        0.5 * TILE_SIZE * PIXELS_PER_METER,
-                        -1.0,
-                    )),
-                    texture: match tyle_type {
-                        TileType::Countryside => asset_server.load("sprites/tiles/countryside.png"),
-                        TileType::Village => asset_server.load("sprites/tiles/village.png"),
-                        TileType::CityMinor => asset_server.load("sprites/tiles/city_minor.png"),
-                        TileType::CityMajor => asset_server.load("sprites/tiles/city_major.png"),
-                        TileType::CityMetropolis => {
-                            asset_server.load("sprites/tiles/city_metropolis.png")
-                        }
-                    },
+        if present_tiles.contains(&tile_nr) {
+            continue;
+        }
+        let tyle_type = world.get_tile(tile_nr);
+        // The assets are still loading, this tile is spawned as soon as they are there
+        let (Some(layout), Some(catalogue)) = (tile_layouts.get(tyle_type), catalogue.as_deref())
+        else {
+            continue;
+        };
+        let collidables = spawn_collidables(
+            &mut commands,
+            layout,
+            catalogue,
+            &world,
+            tile_nr,
+            asset_server,
+        );
+        // The light and weather where the tile lies color it
+        let tint = world.conditions(tile_nr as f32 * TILE_SIZE).tint();
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: tint,
                     ..Default::default()
                 },
-                Tile {
-                    tile_type: tyle_type,
-                    order: tile_nr,
-                    collidables,
-                },
-            ));
-        }
+                transform: Transform::from_translation(Vec3::new(
+                    tile_nr as f32 * TILE_SIZE * PIXELS_PER_METER,
+                    0.5 * TILE_SIZE * PIXELS_PER_METER,
+                    -1.0,
+                )),
+                texture: load_texture(asset_server, &layout.background),
+                ..Default::default()
+            },
+            Tile {
+                tile_type: tyle_type,
+                order: tile_nr,
+                collidables,
+            },
+        ));
     }
 }
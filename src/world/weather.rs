@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use std::f32::consts::TAU;
+
+use crate::flight::FlightModel;
+
+// The weather and the time of day change with the distance, not with the time: a place in the world always
+// looks and flies the same. The further out, the more likely the weather turns bad.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weather {
+    Clear,
+    Fog,
+    Rain,  // the wet stork is slowed down more by the air
+    Storm, // rain with strong gusts
+}
+
+// The weather and the light at one place in the world
+#[derive(Clone, Copy, Debug)]
+pub struct Conditions {
+    pub weather: Weather,
+    pub daylight: f32, // 1 at noon, 0 at midnight
+}
+
+pub const STRETCH_LENGTH: f32 = 100.0; // in meters, the weather stays the same over one stretch
+const DAY_LENGTH: f32 = 800.0; // in meters, from noon to noon
+
+// The chance that a stretch has bad weather grows with every stretch, up to a limit
+const BAD_WEATHER_RAMP: f64 = 0.06;
+const MAX_BAD_WEATHER: f64 = 0.7;
+// Of the bad weather, storms become more likely further out too
+const STORM_RAMP: f64 = 0.03;
+const MAX_STORM: f64 = 0.5;
+
+const CLEAR_VISIBILITY: f32 = 25.0; // in meters, more than fits on the screen
+const NIGHT_VISIBILITY: f32 = 8.0; // in meters
+
+const NIGHT_TINT: Color = Color::rgb(0.25, 0.3, 0.5);
+const NIGHT_VEIL: Color = Color::rgb(0.02, 0.02, 0.08);
+const FOG_VEIL: Color = Color::rgb(0.8, 0.82, 0.85);
+
+impl Weather {
+    // The weather gets worse the same way in both directions from the origin
+    pub fn generate(stretch: i64, rng: &mut StdRng) -> Self {
+        let stretch = stretch.unsigned_abs();
+        let bad_weather = (stretch as f64 * BAD_WEATHER_RAMP).min(MAX_BAD_WEATHER);
+        if !rng.gen_bool(bad_weather) {
+            return Weather::Clear;
+        }
+        if rng.gen_bool((stretch as f64 * STORM_RAMP).min(MAX_STORM)) {
+            return Weather::Storm;
+        }
+        *[Weather::Fog, Weather::Rain].choose(rng).unwrap()
+    }
+}
+
+impl Conditions {
+    // The run starts at noon
+    pub fn at(x: f32, weather: Weather) -> Self {
+        Self {
+            weather,
+            daylight: 0.5 + 0.5 * (TAU * x / DAY_LENGTH).cos(),
+        }
+    }
+
+    // The flight model with the air resistance of this weather
+    pub fn flight_model(&self, flight_model: &FlightModel) -> FlightModel {
+        let drag = match self.weather {
+            Weather::Clear | Weather::Fog => 1.0,
+            Weather::Rain => 2.0,
+            Weather::Storm => 2.5,
+        };
+        FlightModel {
+            air_resistance: flight_model.air_resistance * drag,
+            ..*flight_model
+        }
+    }
+
+    // How much stronger the gusts blow than in clear weather
+    pub fn gust_factor(&self) -> f32 {
+        match self.weather {
+            Weather::Storm => 3.0,
+            _ => 1.0,
+        }
+    }
+
+    // Raindrops per second on the screen
+    pub fn rain(&self) -> f32 {
+        match self.weather {
+            Weather::Clear | Weather::Fog => 0.0,
+            Weather::Rain => 80.0,
+            Weather::Storm => 200.0,
+        }
+    }
+
+    // How far ahead the stork can see, in meters
+    pub fn visibility(&self) -> f32 {
+        let weather = match self.weather {
+            Weather::Clear => CLEAR_VISIBILITY,
+            Weather::Fog => 4.0,
+            Weather::Rain => 12.0,
+            Weather::Storm => 7.0,
+        };
+        let light = NIGHT_VISIBILITY + (CLEAR_VISIBILITY - NIGHT_VISIBILITY) * self.daylight;
+        weather.min(light)
+    }
+
+    // How much of the veil shows, from none on a clear day to most of it when the stork can hardly see
+    pub fn veil_strength(&self) -> f32 {
+        1.0 - self.visibility() / CLEAR_VISIBILITY
+    }
+
+    // The color that hides what is out of sight: the fog, or the dark of the night
+    pub fn veil(&self) -> Color {
+        if self.weather == Weather::Fog && self.daylight > 0.3 {
+            FOG_VEIL
+        } else {
+            NIGHT_VEIL
+        }
+    }
+
+    // The color that sprites are multiplied with in this light and weather
+    pub fn tint(&self) -> Color {
+        let weather = match self.weather {
+            Weather::Clear => Vec3::ONE,
+            Weather::Fog => Vec3::new(0.85, 0.85, 0.9),
+            Weather::Rain => Vec3::new(0.75, 0.78, 0.85),
+            Weather::Storm => Vec3::new(0.55, 0.55, 0.65),
+        };
+        let night = Vec3::new(NIGHT_TINT.r(), NIGHT_TINT.g(), NIGHT_TINT.b());
+        let light = night.lerp(Vec3::ONE, self.daylight) * weather;
+        Color::rgb(light.x, light.y, light.z)
+    }
+
+    pub fn describe(&self) -> String {
+        let weather = match self.weather {
+            Weather::Clear => "clear",
+            Weather::Fog => "fog",
+            Weather::Rain => "rain",
+            Weather::Storm => "storm",
+        };
+        let time = match self.daylight {
+            x if x > 0.75 => "day",
+            x if x > 0.25 => "twilight",
+            _ => "night",
+        };
+        format!("{weather}, {time}")
+    }
+}
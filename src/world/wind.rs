@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use std::{f32::consts::TAU, ops::Range};
+
+use crate::PIXELS_PER_METER;
+
+use super::{
+    collidables::Collidable,
+    tiles::{TileType, TILE_SIZE},
+    World,
+};
+
+// The air over the world moves: a steady wind that changes from tile to tile, gusts on top of it,
+// thermals rising over the fields of the countryside and the outskirts, and updrafts against tall
+// buildings. Storms blow the gusts harder, and the wind picks up as the world gets harder.
+// It all follows from the seed of the world and the time into the run, so runs with the same seed
+// and input stay the same.
+
+// How the air moves over one tile
+#[derive(Clone, Copy, Debug)]
+pub struct TileWind {
+    steady: f32,      // in meters per second, positive blows to the right
+    gust: f32,        // the strongest gust on top of the steady wind, in meters per second
+    gust_period: f32, // in seconds
+    gust_phase: f32,  // in radians
+    thermal: Option<Thermal>,
+}
+
+// A column of rising air
+#[derive(Clone, Copy, Debug)]
+struct Thermal {
+    x: f32,        // of its center, in meters
+    strength: f32, // upwards at its center near the ground, in meters per second
+}
+
+const MAX_STEADY_WIND: f32 = 1.5; // in meters per second
+const MAX_GUST: f32 = 1.5; // in meters per second
+const GUST_PERIOD: Range<f32> = 4.0..12.0; // in seconds
+const THERMAL_CHANCE: f64 = 0.6; // of a tile with fields having a thermal
+const THERMAL_STRENGTH: Range<f32> = 0.8..1.8; // in meters per second
+const THERMAL_WIDTH: f32 = 1.5; // in meters
+const THERMAL_CEILING: f32 = 8.0; // where the thermals have faded out, in meters
+const UPDRAFT_REACH: f32 = 1.5; // how far in front of a building the air rises, in meters
+const UPDRAFT_OVERSHOOT: f32 = 1.0; // how far above a building the air still rises, in meters
+
+impl TileWind {
+    pub fn generate(tile_nr: i64, tile_type: TileType, rng: &mut StdRng) -> Self {
+        let steady = rng.gen_range(-MAX_STEADY_WIND..=MAX_STEADY_WIND);
+        let gust = rng.gen_range(0.0..=MAX_GUST);
+        let gust_period = rng.gen_range(GUST_PERIOD);
+        let gust_phase = rng.gen_range(0.0..TAU);
+        let thermal = (matches!(tile_type, TileType::Countryside | TileType::Outskirts)
+            && rng.gen_bool(THERMAL_CHANCE))
+        .then(|| Thermal {
+            x: (tile_nr as f32 + rng.gen_range(-0.4..0.4)) * TILE_SIZE,
+            strength: rng.gen_range(THERMAL_STRENGTH),
+        });
+        Self {
+            steady,
+            gust,
+            gust_period,
+            gust_phase,
+            thermal,
+        }
+    }
+
+    // Gusts blow the same way as the steady wind, in short bursts, and stronger in a storm
+    fn horizontal(&self, time: f32, gust_factor: f32) -> f32 {
+        let burst = (TAU * time / self.gust_period + self.gust_phase)
+            .sin()
+            .max(0.0)
+            .powi(4);
+        self.steady + self.gust * gust_factor * burst * self.steady.signum()
+    }
+
+    fn thermal_lift(&self, position: Vec2) -> f32 {
+        let Some(thermal) = self.thermal else {
+            return 0.0;
+        };
+        let across = (position.x - thermal.x) / THERMAL_WIDTH;
+        let fade = (1.0 - position.y / THERMAL_CEILING).clamp(0.0, 1.0);
+        thermal.strength * (-across.powi(2)).exp() * fade
+    }
+}
+
+// The time into the run, that the gusts follow
+#[derive(Resource, Default)]
+pub struct Wind {
+    time: f32, // in seconds
+}
+
+impl Wind {
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    // How the air moves at `position`, in meters per second
+    pub fn at<'a>(
+        &self,
+        world: &mut World,
+        position: Vec2,
+        collidables: impl IntoIterator<Item = (&'a Collidable, &'a Transform)>,
+    ) -> Vec2 {
+        // Every tile is centered on a multiple of the tile size. In between, the wind of one tile smoothly
+        // turns into the wind of the next.
+        let tile = (position.x / TILE_SIZE).floor();
+        let blend = position.x / TILE_SIZE - tile;
+        let blend = blend * blend * (3.0 - 2.0 * blend);
+        let (left, right) = (
+            world.tile_wind(tile as i64),
+            world.tile_wind(tile as i64 + 1),
+        );
+        let gust_factor = world.conditions(position.x).gust_factor();
+        let horizontal = (left.horizontal(self.time, gust_factor) * (1.0 - blend)
+            + right.horizontal(self.time, gust_factor) * blend)
+            * world.difficulty(position.x).wind_scale;
+        let thermals = left.thermal_lift(position) + right.thermal_lift(position);
+        let updrafts: f32 = collidables
+            .into_iter()
+            .map(|(collidable, transform)| updraft(collidable, transform, position, horizontal))
+            .sum();
+        Vec2::new(horizontal, thermals + updrafts)
+    }
+}
+
+// The wind rises where it blows against the wall of a building with an updraft, up to a bit above its roof
+fn updraft(collidable: &Collidable, transform: &Transform, position: Vec2, wind: f32) -> f32 {
+    if collidable.updraft == 0.0 || wind == 0.0 {
+        return 0.0;
+    }
+    let center = collidable.hitbox_center(transform).truncate() / PIXELS_PER_METER;
+    let half_size = collidable.size / PIXELS_PER_METER / 2.0;
+    // The wall the wind blows against is on the side it comes from
+    let in_front = (center.x - position.x) * wind.signum() - half_size.x;
+    if !(0.0..UPDRAFT_REACH).contains(&in_front)
+        || position.y > center.y + half_size.y + UPDRAFT_OVERSHOOT
+    {
+        return 0.0;
+    }
+    collidable.updraft * wind.abs() / MAX_STEADY_WIND * (1.0 - in_front / UPDRAFT_REACH)
+}